@@ -0,0 +1,117 @@
+// Canonical JSON serialization following RFC 8785, the JSON Canonicalization Scheme (JCS):
+// no whitespace, object members sorted by the UTF-16 code units of their names,
+// and numbers formatted like ECMAScript does for IEEE 754 doubles.
+
+use crate::error::Error;
+
+use serde_json::{Number, Value};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    pub fn from_str(s: &str) -> Result<HashAlgorithm, Error> {
+        match s {
+            "SHA256" => Ok(HashAlgorithm::Sha256),
+            "XXH3" => Ok(HashAlgorithm::Xxh3),
+            _ => Err("ERR wrong hash algorithm".into()),
+        }
+    }
+}
+
+pub fn to_string(value: &Value) -> Result<String, Error> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+///
+/// Returns the hex digest of the canonical form of value
+///
+pub fn hash(value: &Value, algorithm: &HashAlgorithm) -> Result<String, Error> {
+    let canonical = to_string(value)?;
+    Ok(match algorithm {
+        HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(canonical.as_bytes())),
+        HashAlgorithm::Xxh3 => format!("{:016x}", twox_hash::xxh3::hash64(canonical.as_bytes())),
+    })
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), Error> {
+    match value {
+        Value::Number(n) => out.push_str(&format_number(n)?),
+        // serde_json escapes strings like ECMAScript's JSON.stringify
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&value.to_string()),
+        Value::Array(arr) => {
+            out.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(v, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(Vec<u16>, &String, &Value)> = map
+                .iter()
+                .map(|(k, v)| (k.encode_utf16().collect(), k, v))
+                .collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+
+            out.push('{');
+            for (i, (_, k, v)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(k.to_string()).to_string());
+                out.push(':');
+                write_value(v, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+///
+/// Formats a number like ECMAScript's Number.prototype.toString
+///
+fn format_number(n: &Number) -> Result<String, Error> {
+    let f = n
+        .as_f64()
+        .filter(|f| f.is_finite())
+        .ok_or_else(|| Error::from(format!("ERR number {} is out of the canonical range", n)))?;
+    if f == 0.0 {
+        return Ok("0".to_string());
+    }
+
+    // The shortest digits that round trip, like "1.2345e-7"
+    let exp_form = format!("{:e}", f.abs());
+    let mut parts = exp_form.split('e');
+    let digits = parts.next().unwrap().replace('.', "");
+    let exponent: i32 = parts.next().unwrap().parse().unwrap();
+
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+    let res = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let mantissa = if k == 1 {
+            digits
+        } else {
+            format!("{}.{}", &digits[..1], &digits[1..])
+        };
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        format!("{}e{}{}", mantissa, sign, (n - 1).abs())
+    };
+
+    Ok(if f < 0.0 { format!("-{}", res) } else { res })
+}
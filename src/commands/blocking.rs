@@ -0,0 +1,243 @@
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_longlong, c_void};
+use std::slice;
+
+use serde_json::Value;
+
+use redis_module::{raw, Context, NextArg, RedisError, RedisValue};
+
+use crate::redisjson::{Format, RedisJSON};
+use crate::{backwards_compat_path, do_json_arr_pop, REDIS_JSON_TYPE};
+
+/// State kept by Redis for a client blocked on JSON.BARRPOP, released by `free_privdata`
+struct BlockedPop {
+    key: String,
+    path: String,
+    index: i64,
+}
+
+///
+/// Wakes up the clients blocked on `key`.
+///
+/// Should be called by every command that might make an array non-empty.
+/// The blocked clients re-check their array, so a spurious signal is harmless.
+///
+pub fn signal_key_ready(ctx: &Context, key: &str) {
+    unsafe {
+        let key_name = create_string(ctx.ctx, key);
+        raw::RedisModule_SignalKeyAsReady.unwrap()(ctx.ctx, key_name);
+        raw::RedisModule_FreeString.unwrap()(ctx.ctx, key_name);
+    }
+}
+
+///
+/// Registers the blocking commands.
+///
+/// These can't be declared with the `redis_module!` macro, since it always sends the
+/// reply returned by the command, while a blocked client must not get any reply until
+/// it's unblocked.
+///
+pub fn register(ctx: *mut raw::RedisModuleCtx) -> c_int {
+    let name = CString::new("json.barrpop").unwrap();
+    let flags = CString::new("write").unwrap();
+    unsafe {
+        raw::RedisModule_CreateCommand.unwrap()(
+            ctx,
+            name.as_ptr(),
+            Some(barrpop_command),
+            flags.as_ptr(),
+            1,
+            1,
+            1,
+        )
+    }
+}
+
+extern "C" fn barrpop_command(
+    ctx: *mut raw::RedisModuleCtx,
+    argv: *mut *mut raw::RedisModuleString,
+    argc: c_int,
+) -> c_int {
+    let context = Context::new(ctx);
+    let args = unsafe { slice::from_raw_parts(argv, argc as usize) }
+        .iter()
+        .map(|arg| string_from_ptr(*arg))
+        .collect::<Result<Vec<String>, RedisError>>();
+
+    match args.and_then(|args| barrpop(&context, args)) {
+        Ok(Some(res)) => context.reply(Ok(res)) as c_int,
+        // The client is blocked, the reply is sent by `barrpop_reply` or `barrpop_timeout`
+        Ok(None) => raw::Status::Ok as c_int,
+        Err(e) => context.reply(Err(e)) as c_int,
+    }
+}
+
+// JSON.BARRPOP <key> <path> [index] <timeout>
+//
+// timeout is in seconds, 0 blocks forever (like BLPOP)
+fn barrpop<I>(ctx: &Context, args: I) -> Result<Option<RedisValue>, RedisError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+
+    let mut rest: Vec<String> = args.collect();
+    let timeout = rest.pop().ok_or(RedisError::WrongArity)?;
+    let index = match rest.pop() {
+        Some(index) => index.parse()?,
+        None => i64::MAX,
+    };
+    if !rest.is_empty() {
+        return Err(RedisError::WrongArity);
+    }
+
+    // nan, inf and timeouts too large for milliseconds would otherwise turn into
+    // "block forever" when converted
+    let timeout_ms = timeout
+        .parse::<f64>()
+        .ok()
+        .map(|timeout| timeout * 1000.0)
+        .filter(|timeout_ms| timeout_ms.is_finite() && *timeout_ms < c_longlong::MAX as f64)
+        .ok_or(RedisError::Str(
+            "ERR timeout is not a float or out of range",
+        ))?;
+    if timeout_ms < 0.0 {
+        return Err("ERR timeout is negative".into());
+    }
+
+    let pop = BlockedPop { key, path, index };
+    if let Some(res) = try_pop(ctx, &pop)? {
+        return Ok(Some(res));
+    }
+
+    // Like BLPOP, don't block inside MULTI or a Lua script, which can't be suspended
+    let flags = unsafe { raw::RedisModule_GetContextFlags.unwrap()(ctx.ctx) } as u32;
+    if flags & (raw::REDISMODULE_CTX_FLAGS_MULTI | raw::REDISMODULE_CTX_FLAGS_LUA) != 0 {
+        return Ok(Some(RedisValue::None));
+    }
+
+    let privdata = Box::into_raw(Box::new(pop));
+    let blocked = unsafe {
+        let key_name = create_string(ctx.ctx, &(*privdata).key);
+        let mut keys = [key_name];
+        let blocked = raw::RedisModule_BlockClientOnKeys.unwrap()(
+            ctx.ctx,
+            Some(barrpop_reply),
+            Some(barrpop_timeout),
+            Some(barrpop_free),
+            timeout_ms as c_longlong,
+            keys.as_mut_ptr(),
+            1,
+            privdata as *mut c_void,
+        );
+        raw::RedisModule_FreeString.unwrap()(ctx.ctx, key_name);
+        blocked
+    };
+
+    if blocked.is_null() {
+        drop(unsafe { Box::from_raw(privdata) });
+        return Err("ERR failed to block the client".into());
+    }
+
+    Ok(None)
+}
+
+///
+/// Pops from the array if it has elements, and replicates the pop.
+/// Returns `None` when the key, the path or the array elements don't exist (yet).
+/// Any other error, like an invalid path, is returned rather than blocking on it.
+///
+fn try_pop(ctx: &Context, pop: &BlockedPop) -> Result<Option<RedisValue>, RedisError> {
+    let key = ctx.open_key_writable(&pop.key);
+    let doc = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => doc,
+        None => return Ok(None),
+    };
+
+    match doc.get_values(&pop.path)?.first() {
+        Some(Value::Array(arr)) if arr.is_empty() => return Ok(None),
+        Some(Value::Array(_)) => {}
+        Some(_) => return Err("ERR wrong type of path value - expected array".into()),
+        None => return Ok(None),
+    }
+
+    let mut res = Value::Null;
+    doc.value_op(&pop.path, |value| {
+        do_json_arr_pop(pop.index, &mut res, value)
+    })?;
+    replicate_pop(ctx, pop);
+    Ok(Some(RedisJSON::serialize(&res, Format::JSON)?.into()))
+}
+
+// Replicas and the AOF get the pop as the JSON.ARRPOP it's equivalent to, since
+// JSON.BARRPOP itself could block there or pop at another time
+fn replicate_pop(ctx: &Context, pop: &BlockedPop) {
+    let cmd = CString::new("JSON.ARRPOP").unwrap();
+    let fmt = CString::new("bbl").unwrap();
+    unsafe {
+        raw::RedisModule_Replicate.unwrap()(
+            ctx.ctx,
+            cmd.as_ptr(),
+            fmt.as_ptr(),
+            pop.key.as_ptr() as *const c_char,
+            pop.key.len(),
+            pop.path.as_ptr() as *const c_char,
+            pop.path.len(),
+            pop.index as c_longlong,
+        );
+    }
+}
+
+// Like the commands registered by the `redis_module!` macro, a non UTF-8 argument is an
+// error rather than being replaced, which would block on a different key
+fn string_from_ptr(s: *const raw::RedisModuleString) -> Result<String, RedisError> {
+    let mut len = 0;
+    let bytes = unsafe {
+        let ptr = raw::RedisModule_StringPtrLen.unwrap()(s, &mut len);
+        slice::from_raw_parts(ptr as *const u8, len as usize)
+    };
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| RedisError::Str("ERR UTF8 encoding error in handler args"))
+}
+
+unsafe fn create_string(ctx: *mut raw::RedisModuleCtx, s: &str) -> *mut raw::RedisModuleString {
+    raw::RedisModule_CreateString.unwrap()(ctx, s.as_ptr() as *const c_char, s.len())
+}
+
+extern "C" fn barrpop_reply(
+    ctx: *mut raw::RedisModuleCtx,
+    _argv: *mut *mut raw::RedisModuleString,
+    _argc: c_int,
+) -> c_int {
+    let context = Context::new(ctx);
+    let pop = unsafe {
+        &*(raw::RedisModule_GetBlockedClientPrivateData.unwrap()(ctx) as *const BlockedPop)
+    };
+
+    let res = match try_pop(&context, pop) {
+        Ok(Some(res)) => Ok(res),
+        // The array is still empty: keep the client blocked
+        Ok(None) => return raw::Status::Err as c_int,
+        Err(e) => Err(e),
+    };
+
+    context.reply(res);
+    raw::Status::Ok as c_int
+}
+
+extern "C" fn barrpop_timeout(
+    ctx: *mut raw::RedisModuleCtx,
+    _argv: *mut *mut raw::RedisModuleString,
+    _argc: c_int,
+) -> c_int {
+    Context::new(ctx).reply(Ok(RedisValue::None)) as c_int
+}
+
+extern "C" fn barrpop_free(_ctx: *mut raw::RedisModuleCtx, privdata: *mut c_void) {
+    if !privdata.is_null() {
+        drop(unsafe { Box::from_raw(privdata as *mut BlockedPop) });
+    }
+}
@@ -1 +1,2 @@
+pub mod blocking;
 pub mod index;
@@ -1,16 +1,20 @@
 #[macro_use]
 extern crate redis_module;
 
+use bigdecimal::BigDecimal;
 use redis_module::native_types::RedisType;
 use redis_module::raw::RedisModuleTypeMethods;
 use redis_module::{raw as rawmod, NextArg};
 use redis_module::{Context, RedisError, RedisResult, RedisValue, REDIS_OK};
 use serde_json::{Number, Value};
 
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::{i64, usize};
 
 mod array_index;
 mod backward;
+mod canonical;
 mod commands;
 mod error;
 mod nodevisitor;
@@ -18,9 +22,10 @@ mod redisjson;
 mod schema; // TODO: Remove
 
 use crate::array_index::ArrayIndex;
-use crate::commands::index;
+use crate::canonical::HashAlgorithm;
+use crate::commands::{blocking, index};
 use crate::error::Error;
-use crate::redisjson::{Format, Path, RedisJSON, SetOptions};
+use crate::redisjson::{Format, Path, RedisJSON, SetOptions, SortOrder, SortType, StrUnit};
 
 static REDIS_JSON_TYPE: RedisType = RedisType::new(
     "ReJSON-RL",
@@ -85,7 +90,11 @@ fn json_del(ctx: &Context, args: Vec<String>) -> RedisResult {
 }
 
 ///
-/// JSON.SET <key> <path> <json> [NX | XX | FORMAT <format> | INDEX <index>]
+/// JSON.SET <key> <path> <json> [NX | XX | MKPATH | FORMAT <format> | INDEX <index>]
+///
+/// A static path sets a single value and replies OK, or nil when nothing was set.
+/// Any other path, like a filter, only updates the values it matches and replies
+/// with their count.
 ///
 fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
     let mut args = args.into_iter().skip(1);
@@ -96,6 +105,7 @@ fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
 
     let mut format = Format::JSON;
     let mut set_option = SetOptions::None;
+    let mut mkpath = false;
     let mut index = None;
 
     loop {
@@ -103,6 +113,7 @@ fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
             match s.to_uppercase().as_str() {
                 "NX" => set_option = SetOptions::NotExists,
                 "XX" => set_option = SetOptions::AlreadyExists,
+                "MKPATH" => mkpath = true,
                 "FORMAT" => {
                     format = Format::from_str(args.next_string()?.as_str())?;
                 }
@@ -119,12 +130,30 @@ fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
     let redis_key = ctx.open_key_writable(&key);
     let current = redis_key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?;
 
+    if !RedisJSON::is_static_path(&path)? {
+        // A path that can select many values only updates the existing ones
+        return match (current, set_option) {
+            (Some(doc), SetOptions::None) | (Some(doc), SetOptions::AlreadyExists) => {
+                let updated = doc.update_values(&value, &path, format)?;
+                if updated > 0 {
+                    if let Some(index) = index {
+                        index::add_document(&key, &index, &doc)?;
+                    }
+                    blocking::signal_key_ready(ctx, &key);
+                }
+                Ok(updated.into())
+            }
+            _ => Ok(RedisValue::Integer(0)),
+        };
+    }
+
     match (current, set_option) {
         (Some(ref mut doc), ref op) => {
-            if doc.set_value(&value, &path, op, format)? {
+            if doc.set_value(&value, &path, op, mkpath, format)? {
                 if let Some(index) = index {
                     index::add_document(&key, &index, &doc)?;
                 }
+                blocking::signal_key_ready(ctx, &key);
                 REDIS_OK
             } else {
                 Ok(RedisValue::None)
@@ -132,22 +161,28 @@ fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
         }
         (None, SetOptions::AlreadyExists) => Ok(RedisValue::None),
         (None, _) => {
-            let doc = RedisJSON::from_str(&value, &index, format)?;
-            if path == "$" {
-                redis_key.set_value(&REDIS_JSON_TYPE, doc)?;
+            let doc = if path == "$" {
+                RedisJSON::from_str(&value, &index, format)?
+            } else if mkpath {
+                let mut doc = RedisJSON::from_str("{}", &index, Format::JSON)?;
+                doc.set_value(&value, &path, &SetOptions::None, mkpath, format)?;
+                doc
+            } else {
+                return Err("ERR new objects must be created at the root".into());
+            };
 
-                if let Some(index) = index {
-                    // FIXME: We need to get the value even though we just set it,
-                    // since the original doc is consumed by set_value.
-                    // Can we do better than this?
-                    let doc = redis_key.get_value(&REDIS_JSON_TYPE)?.unwrap();
-                    index::add_document(&key, &index, doc)?;
-                }
+            redis_key.set_value(&REDIS_JSON_TYPE, doc)?;
 
-                REDIS_OK
-            } else {
-                Err("ERR new objects must be created at the root".into())
+            if let Some(index) = index {
+                // FIXME: We need to get the value even though we just set it,
+                // since the original doc is consumed by set_value.
+                // Can we do better than this?
+                let doc = redis_key.get_value(&REDIS_JSON_TYPE)?.unwrap();
+                index::add_document(&key, &index, doc)?;
             }
+            blocking::signal_key_ready(ctx, &key);
+
+            REDIS_OK
         }
     }
 }
@@ -158,7 +193,23 @@ fn json_set(ctx: &Context, args: Vec<String>) -> RedisResult {
 ///         [NEWLINE line-break-string]
 ///         [SPACE space-string]
 ///         [NOESCAPE]
-///         [path ...]
+///         [CANONICAL]
+///         [MAXDEPTH n]
+///         [path [DEFAULT json] ...]
+///         [PROJECT path [AS alias] ...]
+///         [EXCLUDE path ...]
+///
+/// CANONICAL serializes the reply with the RFC 8785 JSON Canonicalization Scheme
+///
+/// DEFAULT is the value of the path before it when that path doesn't exist,
+/// or of all the paths when it's given before them
+///
+/// MAXDEPTH replaces the containers more than n levels below a replied value with
+/// a stub holding their type and size
+///
+/// PROJECT replies with an object of the values matched by its paths, nested like in
+/// the document or under their alias, and EXCLUDE with the document without the values
+/// matched by its paths. Both take the paths up to the next option.
 ///
 /// TODO add support for multi path
 fn json_get(ctx: &Context, args: Vec<String>) -> RedisResult {
@@ -166,20 +217,18 @@ fn json_get(ctx: &Context, args: Vec<String>) -> RedisResult {
     let key = args.next_string()?;
 
     let mut paths: Vec<Path> = vec![];
-    let mut first_loop = true;
     let mut format = Format::JSON;
+    let mut canonical = false;
+    let mut default = None;
+    let mut projections: Vec<(Path, Option<String>)> = vec![];
+    let mut excludes: Vec<Path> = vec![];
+    let mut list = None;
+    let mut max_depth = None;
     loop {
         let arg = match args.next_string() {
             Ok(s) => s,
-            Err(_) => {
-                // path is optional -> no path found on the first loop we use root "$"
-                if first_loop {
-                    paths.push(Path::new("$".to_string()));
-                }
-                break;
-            }
+            Err(_) => break,
         };
-        first_loop = false;
 
         match arg.as_str() {
             "INDENT" => {
@@ -194,19 +243,72 @@ fn json_get(ctx: &Context, args: Vec<String>) -> RedisResult {
             "NOESCAPE" => {
                 continue;
             } // TODO add support
+            "CANONICAL" => {
+                canonical = true;
+            }
             "FORMAT" => {
                 format = Format::from_str(args.next_string()?.as_str())?;
             }
-            _ => {
-                paths.push(Path::new(arg));
+            "MAXDEPTH" => {
+                max_depth = Some(args.next_u64()? as usize);
+            }
+            "PROJECT" | "EXCLUDE" => {
+                list = Some(arg);
             }
+            "AS" if list.as_deref() == Some("PROJECT") && !projections.is_empty() => {
+                projections.last_mut().unwrap().1 = Some(args.next_string()?);
+            }
+            "DEFAULT" => {
+                let value = RedisJSON::parse_str(&args.next_string()?, Format::JSON)?;
+                match paths.last_mut() {
+                    Some(path) => path.default = Some(value),
+                    None => default = Some(value),
+                }
+            }
+            _ => match list.as_deref() {
+                Some("PROJECT") => projections.push((Path::new(arg), None)),
+                Some("EXCLUDE") => excludes.push(Path::new(arg)),
+                _ => paths.push(Path::new(arg)),
+            },
         };
     }
 
+    let projecting = !projections.is_empty() || !excludes.is_empty();
+    if projecting && !paths.is_empty() {
+        return Err("ERR PROJECT and EXCLUDE can't be used with paths".into());
+    }
+    // path is optional -> no path found after the options we use root "$"
+    if paths.is_empty() {
+        paths.push(Path::new("$".to_string()));
+    }
+
+    if let Some(default) = default {
+        for path in paths.iter_mut().filter(|p| p.default.is_none()) {
+            path.default = Some(default.clone());
+        }
+    }
+
     let key = ctx.open_key_writable(&key);
     let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
-        Some(doc) => if paths.len() == 1 {
-            doc.to_string(&paths[0].fixed, format)?
+        Some(doc) => if projecting || max_depth.is_some() {
+            let value = if projecting {
+                let value = doc.project(&projections, &excludes)?;
+                match max_depth {
+                    Some(max_depth) => RedisJSON::truncate(&value, max_depth),
+                    None => value,
+                }
+            } else {
+                doc.get_results(&mut paths, max_depth)?
+            };
+            if canonical {
+                canonical::to_string(&value)?
+            } else {
+                RedisJSON::serialize(&value, format)?
+            }
+        } else if canonical {
+            doc.to_canonical(&mut paths)?
+        } else if paths.len() == 1 {
+            doc.to_string(&paths[0].fixed, &paths[0].default, format)?
         } else {
             // can't be smaller than 1
             doc.to_json(&mut paths)?
@@ -219,9 +321,146 @@ fn json_get(ctx: &Context, args: Vec<String>) -> RedisResult {
 }
 
 ///
-/// JSON.MGET <key> [key ...] <path>
+/// JSON.HASH <key> [path] [SHA256|XXH3]
+///
+/// Replies with the hex digest of the canonical form of the value at path, SHA256 by default
+///
+fn json_hash(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+
+    let mut path = None;
+    let mut algorithm = HashAlgorithm::Sha256;
+    while let Some(arg) = args.next() {
+        match HashAlgorithm::from_str(&arg.to_uppercase()) {
+            Ok(a) => algorithm = a,
+            Err(_) if path.is_none() => path = Some(arg),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let path = backwards_compat_path(path.unwrap_or_else(|| "$".to_string()));
+
+    let key = ctx.open_key(&key);
+
+    let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => canonical::hash(doc.get_first(&path)?, &algorithm)?.into(),
+        None => RedisValue::None,
+    };
+
+    Ok(value)
+}
+
+///
+/// JSON.EXISTS <key> <path>
+///
+/// Replies with the number of values matched by path, a value matched more than once
+/// being counted once like in JSON.PATHS
+///
+fn json_exists(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    args.done()?;
+
+    let key = ctx.open_key(&key);
+
+    let count = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => doc.match_count(&path)?,
+        None => 0,
+    };
+
+    Ok(count.into())
+}
+
+///
+/// JSON.PATHS <key> <path>
+///
+/// Replies with the normalized paths of the values matched by path, in document order
+///
+fn json_paths(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    args.done()?;
+
+    let key = ctx.open_key(&key);
+
+    let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => doc.normalized_paths(&path)?.into(),
+        None => RedisValue::None,
+    };
+
+    Ok(value)
+}
+
+///
+/// JSON.EQUALS <key1> <path1> <key2> <path2>
+///
+/// Replies 1 when the values are equal, comparing numbers by value and object
+/// members in any order
+///
+fn json_equals(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key1 = args.next_string()?;
+    let path1 = backwards_compat_path(args.next_string()?);
+    let key2 = args.next_string()?;
+    let path2 = backwards_compat_path(args.next_string()?);
+    args.done()?;
+
+    let key1 = ctx.open_key(&key1);
+    let key2 = ctx.open_key(&key2);
+
+    let value = match (
+        key1.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?,
+        key2.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?,
+    ) {
+        (Some(doc1), Some(doc2)) => {
+            let equal = RedisJSON::values_equal(doc1.get_first(&path1)?, doc2.get_first(&path2)?);
+            RedisValue::Integer(equal as i64)
+        }
+        _ => RedisValue::None,
+    };
+
+    Ok(value)
+}
+
+///
+/// JSON.CONTAINS <key> <path> <json>
+///
+/// Replies 1 when every member and element of json exists in the value at path
+///
+fn json_contains(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let needle = RedisJSON::parse_str(&args.next_string()?, Format::JSON)?;
+    args.done()?;
+
+    let key = ctx.open_key(&key);
+
+    let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => {
+            let contains = RedisJSON::value_contains(doc.get_first(&path)?, &needle);
+            RedisValue::Integer(contains as i64)
+        }
+        None => RedisValue::None,
+    };
+
+    Ok(value)
+}
+
+///
+/// JSON.MGET <key> [key ...] <path> [DEFAULT json]
 ///
 fn json_mget(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let (args, default) = match args.len() {
+        len if len >= 5 && args[len - 2] == "DEFAULT" => (
+            &args[..len - 2],
+            Some(RedisJSON::parse_str(&args[len - 1], Format::JSON)?),
+        ),
+        _ => (&args[..], None),
+    };
+
     if args.len() < 3 {
         return Err(RedisError::WrongArity);
     }
@@ -236,7 +475,7 @@ fn json_mget(ctx: &Context, args: Vec<String>) -> RedisResult {
                 let result = ctx
                     .open_key(key)
                     .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
-                    .map(|doc| doc.to_string(&path, Format::JSON))
+                    .map(|doc| doc.to_string(&path, &default, Format::JSON))
                     .transpose()?;
 
                 Ok(result.into())
@@ -248,10 +487,49 @@ fn json_mget(ctx: &Context, args: Vec<String>) -> RedisResult {
 }
 
 ///
-/// JSON.STRLEN <key> [path]
+/// JSON.STRLEN <key> [path] [UNIT BYTES|CHARS|UTF16]
+///
+/// The length is in UTF-8 bytes unless another unit is given
 ///
-fn json_str_len(ctx: &Context, args: Vec<String>) -> RedisResult {
-    json_len(ctx, args, |doc, path| doc.str_len(path))
+fn json_str_len(ctx: &Context, mut args: Vec<String>) -> RedisResult {
+    // The key and the path are left to json_len, only the trailing UNIT is parsed here
+    let unit = match args.get(3..).unwrap_or_default() {
+        [] => StrUnit::Bytes,
+        [option, unit] if option.to_uppercase() == "UNIT" => {
+            StrUnit::from_str(unit.to_uppercase().as_str())?
+        }
+        _ => return Err("ERR syntax error".into()),
+    };
+    args.truncate(3);
+
+    json_len(ctx, args, |doc, path| doc.str_len(path, &unit))
+}
+
+///
+/// JSON.STRRANGE <key> <path> <start> <end>
+///
+/// start and end are character offsets, both inclusive
+///
+fn json_str_range(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let start = args.next_i64()?;
+    let end = args.next_i64()?;
+
+    args.done()?;
+
+    let key = ctx.open_key(&key);
+    let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => {
+            let s = Value::String(doc.str_range(&path, start, end)?);
+            RedisJSON::serialize(&s, Format::JSON)?.into()
+        }
+        None => RedisValue::None,
+    };
+
+    Ok(value)
 }
 
 ///
@@ -276,172 +554,606 @@ fn json_type(ctx: &Context, args: Vec<String>) -> RedisResult {
 }
 
 ///
-/// JSON.NUMINCRBY <key> <path> <number>
+/// JSON.NUMINCRBY <key> <path> <number> [SATURATE] [CLAMP <min> <max>] [CREATE]
 ///
 fn json_num_incrby(ctx: &Context, args: Vec<String>) -> RedisResult {
-    json_num_op(ctx, args, |i1, i2| i1 + i2, |f1, f2| f1 + f2)
+    json_num_op(
+        ctx,
+        args,
+        &["SATURATE", "CREATE"],
+        |num1, num2, saturate| {
+            num_arith(
+                num1,
+                num2,
+                |i1, i2| Some(i1.saturating_add(i2)),
+                |f1, f2| f1 + f2,
+                Some(|d1, d2| d1 + d2),
+                saturate,
+            )
+        },
+    )
 }
 
 ///
-/// JSON.NUMMULTBY <key> <path> <number>
+/// JSON.NUMMULTBY <key> <path> <number> [SATURATE] [CLAMP <min> <max>]
 ///
 fn json_num_multby(ctx: &Context, args: Vec<String>) -> RedisResult {
-    json_num_op(ctx, args, |i1, i2| i1 * i2, |f1, f2| f1 * f2)
+    json_num_op(ctx, args, &["SATURATE"], |num1, num2, saturate| {
+        num_arith(
+            num1,
+            num2,
+            |i1, i2| Some(i1.saturating_mul(i2)),
+            |f1, f2| f1 * f2,
+            Some(|d1, d2| d1 * d2),
+            saturate,
+        )
+    })
 }
 
 ///
-/// JSON.NUMPOWBY <key> <path> <number>
+/// JSON.NUMPOWBY <key> <path> <number> [SATURATE] [CLAMP <min> <max>]
 ///
 fn json_num_powby(ctx: &Context, args: Vec<String>) -> RedisResult {
-    json_num_op(ctx, args, |i1, i2| i1.pow(i2 as u32), |f1, f2| f1.powf(f2))
+    json_num_op(ctx, args, &["SATURATE"], |num1, num2, saturate| {
+        num_arith(
+            num1,
+            num2,
+            // A negative exponent has no integer result, and an exponent over u32::MAX
+            // gives the same result as the biggest one with the same parity
+            |i1, i2| match u32::try_from(i2) {
+                Ok(i2) => Some(i1.saturating_pow(i2)),
+                Err(_) if i2 > 0 => Some(i1.saturating_pow(u32::MAX - (i2 % 2 == 0) as u32)),
+                Err(_) => None,
+            },
+            |f1, f2| f1.powf(f2),
+            None,
+            saturate,
+        )
+    })
+}
+
+///
+/// JSON.NUMMAXBY <key> <path> <number> [CLAMP <min> <max>]
+///
+/// Keeps the greater number as it's written, so `7` stays an integer against `3.5`
+///
+fn json_num_maxby(ctx: &Context, args: Vec<String>) -> RedisResult {
+    json_num_op(ctx, args, &[], |num1, num2, _| {
+        Ok(Some(pick_number(num1, num2, Ordering::Greater)))
+    })
+}
+
+///
+/// JSON.NUMMINBY <key> <path> <number> [CLAMP <min> <max>]
+///
+/// Keeps the smaller number as it's written
+///
+fn json_num_minby(ctx: &Context, args: Vec<String>) -> RedisResult {
+    json_num_op(ctx, args, &[], |num1, num2, _| {
+        Ok(Some(pick_number(num1, num2, Ordering::Less)))
+    })
 }
 
-fn json_num_op<I, F>(ctx: &Context, args: Vec<String>, op_i64: I, op_f64: F) -> RedisResult
+///
+/// Applies a numeric operation to the values at path.
+///
+/// `op` gets the current value, the number argument and whether SATURATE was given,
+/// and returns `None` when the result isn't a finite number.
+///
+/// With CLAMP the result is then limited to the `min..=max` range.
+///
+/// CREATE initializes a missing value at a static path to 0, creating its missing
+/// parent objects.
+///
+/// CLAMP is accepted by every command, SATURATE and CREATE only when they're in
+/// `options`: they're a syntax error where they don't apply.
+///
+fn json_num_op<O>(ctx: &Context, args: Vec<String>, options: &[&str], op: O) -> RedisResult
 where
-    I: Fn(i64, i64) -> i64,
-    F: Fn(f64, f64) -> f64,
+    O: Fn(&Number, &Number, bool) -> Result<Option<Number>, Error>,
 {
     let mut args = args.into_iter().skip(1);
 
     let key = args.next_string()?;
     let path = backwards_compat_path(args.next_string()?);
-    let number = args.next_string()?;
+    let number = parse_json_number(&args.next_string()?)?;
+
+    let mut saturate = false;
+    let mut clamp = None;
+    let mut create = false;
+    while let Some(s) = args.next() {
+        match s.to_uppercase().as_str() {
+            "SATURATE" if options.contains(&"SATURATE") => saturate = true,
+            "CREATE" if options.contains(&"CREATE") => create = true,
+            "CLAMP" => {
+                let min = parse_json_number(&args.next_string()?)?;
+                let max = parse_json_number(&args.next_string()?)?;
+                if RedisJSON::cmp_numbers(&min, &max) == Ordering::Greater {
+                    return Err("ERR CLAMP min is greater than max".into());
+                }
+                clamp = Some((min, max));
+            }
+            _ => return Err("ERR syntax error".into()),
+        };
+    }
 
     let key = ctx.open_key_writable(&key);
 
     key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
         .ok_or_else(RedisError::nonexistent_key)
         .and_then(|doc| {
-            doc.value_op(&path, |value| {
-                do_json_num_op(&number, value, &op_i64, &op_f64)
+            if create {
+                doc.create_value(&path, Value::from(0))?;
+            }
+            doc.value_op_with_path(&path, |value_path, value| {
+                let curr = match value {
+                    Value::Number(curr) => curr,
+                    _ => return Err(err_json(value, "number")),
+                };
+                // NaN and infinities have no JSON representation
+                let res = op(curr, &number, saturate)?.ok_or_else(|| {
+                    Error::from(format!(
+                        "ERR the result for value {} at path {} is not a finite number",
+                        curr, value_path
+                    ))
+                })?;
+                Ok(Value::Number(match &clamp {
+                    Some((min, max)) => clamp_number(res, min, max),
+                    None => res,
+                }))
             })
             .map(|v| v.to_string().into())
             .map_err(|e| e.into())
         })
 }
 
-fn do_json_num_op<I, F>(
-    in_value: &str,
-    curr_value: &Value,
-    op_i64: I,
+///
+/// An arithmetic operation on two numbers.
+///
+/// Two integer operands use `op_int`, computed in `i128`, or the exact `op_decimal` when
+/// one of them is out of the `i128` range. When `op_int` returns `None` the operation
+/// goes on like for non-integers.
+///
+/// Whatever the size of the operands, the result of two integers must be in the
+/// `i64::MIN..=u64::MAX` range: it's an overflow error otherwise, or with SATURATE it's
+/// clamped to that range.
+///
+/// Other operands use the exact `op_decimal` when given, so decimals keep every digit,
+/// and `op_f64` otherwise or when they have too many digits.
+///
+fn num_arith<I, F>(
+    num1: &Number,
+    num2: &Number,
+    op_int: I,
     op_f64: F,
-) -> Result<Value, Error>
+    op_decimal: Option<fn(&BigDecimal, &BigDecimal) -> BigDecimal>,
+    saturate: bool,
+) -> Result<Option<Number>, Error>
 where
-    I: FnOnce(i64, i64) -> i64,
+    I: FnOnce(i128, i128) -> Option<i128>,
     F: FnOnce(f64, f64) -> f64,
 {
-    if let Value::Number(curr_value) = curr_value {
-        let in_value = &serde_json::from_str(in_value)?;
-        if let Value::Number(in_value) = in_value {
-            let num_res = match (curr_value.as_i64(), in_value.as_i64()) {
-                (Some(num1), Some(num2)) => op_i64(num1, num2).into(),
-                _ => {
-                    let num1 = curr_value.as_f64().unwrap();
-                    let num2 = in_value.as_f64().unwrap();
-                    Number::from_f64(op_f64(num1, num2)).unwrap()
+    let integers = RedisJSON::is_integer(num1) && RedisJSON::is_integer(num2);
+    if integers {
+        let res = match (as_i128(num1), as_i128(num2), op_decimal) {
+            (Some(i1), Some(i2), _) => op_int(i1, i2),
+            (_, _, Some(op_decimal)) => {
+                match (RedisJSON::to_decimal(num1), RedisJSON::to_decimal(num2)) {
+                    (Some(d1), Some(d2)) => Some(decimal_to_i128(&op_decimal(&d1, &d2))),
+                    _ => None,
                 }
-            };
+            }
+            // Without an exact operation, like for a power, an operand out of the `i128`
+            // range is saturated with the same sign and parity: the result is then out of
+            // the 64-bit range too, unless it's 0, 1 or -1, which are still right
+            _ => op_int(saturating_i128(num1), saturating_i128(num2)),
+        };
+        if let Some(res) = res {
+            return int_to_number(res, saturate).map(Some);
+        }
+    }
+
+    let decimal_res = match (
+        op_decimal,
+        RedisJSON::to_decimal(num1),
+        RedisJSON::to_decimal(num2),
+    ) {
+        (Some(op_decimal), Some(d1), Some(d2)) => Some(op_decimal(&d1, &d2)),
+        _ => None,
+    };
+
+    match decimal_res {
+        Some(res) => {
+            let mut res = res.to_string();
+            // Keep a number with a fraction or an exponent from turning into an integer
+            if !integers && !res.contains('.') {
+                res.push_str(".0");
+            }
+            match serde_json::from_str(&res)? {
+                Value::Number(n) if RedisJSON::is_finite(&n) => Ok(Some(n)),
+                _ => Ok(None),
+            }
+        }
+        None => Ok(match (num1.as_f64(), num2.as_f64()) {
+            (Some(f1), Some(f2)) => Number::from_f64(op_f64(f1, f2)),
+            _ => None,
+        }),
+    }
+}
 
-            Ok(Value::Number(num_res))
+///
+/// Returns the number that compares as `ordering` to the other one, or the first one
+/// when they're equal, unchanged
+///
+fn pick_number(num1: &Number, num2: &Number, ordering: Ordering) -> Number {
+    if RedisJSON::cmp_numbers(num2, num1) == ordering {
+        num2.clone()
+    } else {
+        num1.clone()
+    }
+}
+
+fn parse_json_number(json: &str) -> Result<Number, Error> {
+    match RedisJSON::parse_str(json, Format::JSON)? {
+        Value::Number(n) => Ok(n),
+        v => Err(err_json(&v, "number")),
+    }
+}
+
+fn clamp_number(n: Number, min: &Number, max: &Number) -> Number {
+    if RedisJSON::cmp_numbers(&n, min) == Ordering::Less {
+        min.clone()
+    } else if RedisJSON::cmp_numbers(&n, max) == Ordering::Greater {
+        max.clone()
+    } else {
+        n
+    }
+}
+
+fn as_i128(n: &Number) -> Option<i128> {
+    if RedisJSON::is_integer(n) {
+        n.to_string().parse().ok()
+    } else {
+        None
+    }
+}
+
+fn saturating_i128(n: &Number) -> i128 {
+    as_i128(n).unwrap_or_else(|| {
+        let s = n.to_string();
+        let odd = s.bytes().last().map_or(false, |d| (d - b'0') % 2 == 1);
+        if s.starts_with('-') {
+            i128::MIN + odd as i128
         } else {
-            Err(err_json(in_value, "number"))
+            i128::MAX - !odd as i128
         }
+    })
+}
+
+fn decimal_to_i128(d: &BigDecimal) -> i128 {
+    let s = d.to_string();
+    s.parse().unwrap_or_else(|_| {
+        if s.starts_with('-') {
+            i128::MIN
+        } else {
+            i128::MAX
+        }
+    })
+}
+
+fn int_to_number(res: i128, saturate: bool) -> Result<Number, Error> {
+    let (min, max) = (i128::from(i64::MIN), i128::from(u64::MAX));
+    let res = if (min..=max).contains(&res) {
+        res
+    } else if saturate {
+        res.max(min).min(max)
     } else {
-        Err(err_json(curr_value, "number"))
+        return Err("ERR integer overflow - the result is out of the 64-bit integer range".into());
+    };
+
+    if res < 0 {
+        Ok((res as i64).into())
+    } else {
+        Ok((res as u64).into())
+    }
+}
+
+fn err_json(value: &Value, expected_value: &'static str) -> Error {
+    Error::from(format!(
+        "ERR wrong type of path value - expected {} but found {}",
+        expected_value,
+        RedisJSON::value_name(value)
+    ))
+}
+
+///
+/// JSON.STRAPPEND <key> [path] <json-string>
+///
+fn json_str_append(ctx: &Context, args: Vec<String>) -> RedisResult {
+    json_str_op(ctx, args, |json, value| do_json_str_append(json, value))
+}
+
+///
+/// JSON.STRPREPEND <key> [path] <json-string>
+///
+/// Like JSON.STRAPPEND, replies with the new length in UTF-8 bytes
+///
+fn json_str_prepend(ctx: &Context, args: Vec<String>) -> RedisResult {
+    json_str_op(ctx, args, |json, value| do_json_str_prepend(json, value))
+}
+
+fn json_str_op<F>(ctx: &Context, args: Vec<String>, fun: F) -> RedisResult
+where
+    F: Fn(&String, &Value) -> Result<Value, Error>,
+{
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path_or_json = args.next_string()?;
+
+    let path;
+    let json;
+
+    // path is optional
+    if let Ok(val) = args.next_string() {
+        path = backwards_compat_path(path_or_json);
+        json = val;
+    } else {
+        path = "$".to_string();
+        json = path_or_json;
+    }
+
+    let key = ctx.open_key_writable(&key);
+
+    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)
+        .and_then(|doc| {
+            doc.value_op(&path, |value| fun(&json, value))
+                .map(|v| v.as_str().map_or(usize::MAX, |v| v.len()).into())
+                .map_err(|e| e.into())
+        })
+}
+
+fn do_json_str_append(json: &String, value: &Value) -> Result<Value, Error> {
+    value
+        .as_str()
+        .ok_or_else(|| err_json(value, "string"))
+        .and_then(|curr| {
+            let s = parse_json_string(json)?;
+            let new_value = [curr, s.as_str()].concat();
+            Ok(Value::String(new_value))
+        })
+}
+
+fn do_json_str_prepend(json: &String, value: &Value) -> Result<Value, Error> {
+    value
+        .as_str()
+        .ok_or_else(|| err_json(value, "string"))
+        .and_then(|curr| {
+            let s = parse_json_string(json)?;
+            let new_value = [s.as_str(), curr].concat();
+            Ok(Value::String(new_value))
+        })
+}
+
+///
+/// JSON.STRSETRANGE <key> <path> <offset> <json-string>
+///
+/// offset is in characters, a negative offset counts from the end of the string
+/// and an offset equal to the length appends.
+/// The reply is the new length in UTF-8 bytes, like JSON.STRLEN without UNIT,
+/// use JSON.STRLEN ... UNIT CHARS for a length to compute offsets from.
+///
+fn json_str_set_range(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let offset = args.next_i64()?;
+    let json = args.next_string()?;
+
+    args.done()?;
+
+    let key = ctx.open_key_writable(&key);
+
+    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)
+        .and_then(|doc| {
+            doc.value_op(&path, |value| do_json_str_set_range(offset, &json, value))
+                .map(|v| v.as_str().map_or(usize::MAX, |v| v.len()).into())
+                .map_err(|e| e.into())
+        })
+}
+
+fn do_json_str_set_range(offset: i64, json: &String, value: &Value) -> Result<Value, Error> {
+    value
+        .as_str()
+        .ok_or_else(|| err_json(value, "string"))
+        .and_then(|curr| {
+            let s = parse_json_string(json)?;
+
+            let len = curr.chars().count() as i64;
+            if !(-len..=len).contains(&offset) {
+                return Err("ERR offset out of range".into());
+            }
+            let offset = if offset < 0 { len + offset } else { offset } as usize;
+
+            let new_value: String = curr
+                .chars()
+                .take(offset)
+                .chain(s.chars())
+                .chain(curr.chars().skip(offset + s.chars().count()))
+                .collect();
+            Ok(Value::String(new_value))
+        })
+}
+
+fn parse_json_string(json: &str) -> Result<String, Error> {
+    match serde_json::from_str(json)? {
+        Value::String(s) => Ok(s),
+        v => Err(format!("ERR wrong type of value - expected string but found {}", v).into()),
     }
 }
 
-fn err_json(value: &Value, expected_value: &'static str) -> Error {
-    Error::from(format!(
-        "ERR wrong type of path value - expected {} but found {}",
-        expected_value,
-        RedisJSON::value_name(value)
-    ))
+///
+/// JSON.ARRAPPEND <key> <path> <json> [json ...]
+///
+fn json_arr_append(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1).peekable();
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+
+    // We require at least one JSON item to append
+    args.peek().ok_or(RedisError::WrongArity)?;
+
+    let redis_key = ctx.open_key_writable(&key);
+
+    redis_key
+        .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)
+        .and_then(|doc| {
+            doc.value_op(&path, |value| do_json_arr_append(args.clone(), value))
+                .map(|v| v.as_array().map_or(usize::MAX, |v| v.len()).into())
+                .map_err(|e| e.into())
+        })
+        .map(|len| {
+            blocking::signal_key_ready(ctx, &key);
+            len
+        })
+}
+
+fn do_json_arr_append<I>(args: I, value: &Value) -> Result<Value, Error>
+where
+    I: Iterator<Item = String>,
+{
+    value
+        .as_array()
+        .ok_or_else(|| err_json(value, "array"))
+        .and_then(|curr| {
+            let items: Vec<Value> = args
+                .map(|json| RedisJSON::parse_str(&json, Format::JSON))
+                .collect::<Result<_, _>>()?;
+
+            let new_value = [curr.as_slice(), &items].concat();
+            Ok(Value::Array(new_value))
+        })
 }
 
 ///
-/// JSON.STRAPPEND <key> [path] <json-string>
+/// JSON.ARRADDUNIQUE <key> <path> <json> [json ...]
 ///
-fn json_str_append(ctx: &Context, args: Vec<String>) -> RedisResult {
-    let mut args = args.into_iter().skip(1);
+/// Appends only the values that are not already in the array (deep equality)
+///
+fn json_arr_add_unique(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1).peekable();
 
     let key = args.next_string()?;
-    let path_or_json = args.next_string()?;
-
-    let path;
-    let json;
+    let path = backwards_compat_path(args.next_string()?);
 
-    // path is optional
-    if let Ok(val) = args.next_string() {
-        path = backwards_compat_path(path_or_json);
-        json = val;
-    } else {
-        path = "$".to_string();
-        json = path_or_json;
-    }
+    // We require at least one JSON item to add
+    args.peek().ok_or(RedisError::WrongArity)?;
 
-    let key = ctx.open_key_writable(&key);
+    let redis_key = ctx.open_key_writable(&key);
 
-    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+    redis_key
+        .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
         .ok_or_else(RedisError::nonexistent_key)
         .and_then(|doc| {
-            doc.value_op(&path, |value| do_json_str_append(&json, value))
-                .map(|v| v.as_str().map_or(usize::MAX, |v| v.len()).into())
+            doc.value_op(&path, |value| do_json_arr_add_unique(args.clone(), value))
+                .map(|v| v.as_array().map_or(usize::MAX, |v| v.len()).into())
                 .map_err(|e| e.into())
         })
+        .map(|len| {
+            blocking::signal_key_ready(ctx, &key);
+            len
+        })
 }
 
-fn do_json_str_append(json: &String, value: &Value) -> Result<Value, Error> {
+fn do_json_arr_add_unique<I>(args: I, value: &Value) -> Result<Value, Error>
+where
+    I: Iterator<Item = String>,
+{
     value
-        .as_str()
-        .ok_or_else(|| err_json(value, "string"))
+        .as_array()
+        .ok_or_else(|| err_json(value, "array"))
         .and_then(|curr| {
-            let v = serde_json::from_str(json)?;
-            if let Value::String(s) = v {
-                let new_value = [curr, s.as_str()].concat();
-                Ok(Value::String(new_value))
-            } else {
-                Err(format!("ERR wrong type of value - expected string but found {}", v).into())
+            let items: Vec<Value> = args
+                .map(|json| RedisJSON::parse_str(&json, Format::JSON))
+                .collect::<Result<_, _>>()?;
+
+            let mut new_value = curr.to_owned();
+            for item in items {
+                if !new_value.iter().any(|v| RedisJSON::values_equal(v, &item)) {
+                    new_value.push(item);
+                }
             }
+            Ok(Value::Array(new_value))
         })
 }
 
 ///
-/// JSON.ARRAPPEND <key> <path> <json> [json ...]
+/// JSON.ARRREM <key> <path> <json> [COUNT n]
 ///
-fn json_arr_append(ctx: &Context, args: Vec<String>) -> RedisResult {
-    let mut args = args.into_iter().skip(1).peekable();
+/// COUNT follows LREM: n > 0 removes the first n matches, n < 0 the last n matches
+/// and n = 0 (the default) all of them
+///
+fn json_arr_rem(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
 
     let key = args.next_string()?;
     let path = backwards_compat_path(args.next_string()?);
+    let json = args.next_string()?;
 
-    // We require at least one JSON item to append
-    args.peek().ok_or(RedisError::WrongArity)?;
+    let count = match args.next() {
+        Some(s) if s.to_uppercase() == "COUNT" => args.next_i64()?,
+        Some(_) => return Err("ERR syntax error".into()),
+        None => 0,
+    };
+
+    args.done()?;
 
     let key = ctx.open_key_writable(&key);
 
     key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
         .ok_or_else(RedisError::nonexistent_key)
         .and_then(|doc| {
-            doc.value_op(&path, |value| do_json_arr_append(args.clone(), value))
+            doc.value_op(&path, |value| do_json_arr_rem(&json, count, value))
                 .map(|v| v.as_array().map_or(usize::MAX, |v| v.len()).into())
                 .map_err(|e| e.into())
         })
 }
 
-fn do_json_arr_append<I>(args: I, value: &Value) -> Result<Value, Error>
-where
-    I: Iterator<Item = String>,
-{
+fn do_json_arr_rem(json: &str, count: i64, value: &Value) -> Result<Value, Error> {
     value
         .as_array()
         .ok_or_else(|| err_json(value, "array"))
         .and_then(|curr| {
-            let items: Vec<Value> = args
-                .map(|json| serde_json::from_str(&json))
-                .collect::<Result<_, _>>()?;
+            let item = RedisJSON::parse_str(json, Format::JSON)?;
+
+            let limit = match count {
+                0 => usize::MAX,
+                n => n.checked_abs().map_or(usize::MAX, |n| n as usize),
+            };
+
+            let mut removed = 0;
+            let mut keep = |v: &Value| {
+                if removed < limit && RedisJSON::values_equal(v, &item) {
+                    removed += 1;
+                    false
+                } else {
+                    true
+                }
+            };
+
+            let new_value: Vec<Value> = if count < 0 {
+                let mut res: Vec<Value> = curr.iter().rev().filter(|v| keep(v)).cloned().collect();
+                res.reverse();
+                res
+            } else {
+                curr.iter().filter(|v| keep(v)).cloned().collect()
+            };
 
-            let new_value = [curr.as_slice(), &items].concat();
             Ok(Value::Array(new_value))
         })
 }
@@ -471,6 +1183,44 @@ fn json_arr_index(ctx: &Context, args: Vec<String>) -> RedisResult {
     Ok(index.into())
 }
 
+///
+/// JSON.ARRSLICE <key> <path> <start> <stop> [STEP n]
+///
+/// Returns the elements between start and stop (both inclusive) without modifying the array
+///
+fn json_arr_slice(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let start = args.next_i64()?;
+    let stop = args.next_i64()?;
+
+    let step = match args.next() {
+        Some(s) if s.to_uppercase() == "STEP" => args.next_i64()?,
+        Some(_) => return Err("ERR syntax error".into()),
+        None => 1,
+    };
+
+    args.done()?;
+
+    if step < 1 {
+        return Err("ERR step must be a positive integer".into());
+    }
+
+    let key = ctx.open_key(&key);
+
+    let value = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => {
+            let slice = doc.arr_slice(&path, start, stop, step as usize)?;
+            RedisJSON::serialize(&slice, Format::JSON)?.into()
+        }
+        None => RedisValue::None,
+    };
+
+    Ok(value)
+}
+
 ///
 /// JSON.ARRINSERT <key> <path> <index> <json> [json ...]
 ///
@@ -484,9 +1234,10 @@ fn json_arr_insert(ctx: &Context, args: Vec<String>) -> RedisResult {
     // We require at least one JSON item to append
     args.peek().ok_or(RedisError::WrongArity)?;
 
-    let key = ctx.open_key_writable(&key);
+    let redis_key = ctx.open_key_writable(&key);
 
-    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+    redis_key
+        .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
         .ok_or_else(RedisError::nonexistent_key)
         .and_then(|doc| {
             doc.value_op(&path, |value| {
@@ -495,6 +1246,10 @@ fn json_arr_insert(ctx: &Context, args: Vec<String>) -> RedisResult {
             .map(|v| v.as_array().map_or(usize::MAX, |v| v.len()).into())
             .map_err(|e| e.into())
         })
+        .map(|len| {
+            blocking::signal_key_ready(ctx, &key);
+            len
+        })
 }
 
 fn do_json_arr_insert<I>(args: I, index: i64, value: &Value) -> Result<Value, Error>
@@ -507,14 +1262,19 @@ where
         .and_then(|curr| {
             let len = curr.len() as i64;
 
-            if !(-len..len).contains(&index) {
+            if !(-len..=len).contains(&index) {
                 return Err("ERR index out of bounds".into());
             }
 
-            let index = index.normalize(len);
+            // Inserting at len appends, which also allows inserting into an empty array
+            let index = if index == len {
+                len as usize
+            } else {
+                index.normalize(len)
+            };
 
             let items: Vec<Value> = args
-                .map(|json| serde_json::from_str(&json))
+                .map(|json| RedisJSON::parse_str(&json, Format::JSON))
                 .collect::<Result<_, _>>()?;
 
             let mut new_value = curr.to_owned();
@@ -532,57 +1292,186 @@ fn json_arr_len(ctx: &Context, args: Vec<String>) -> RedisResult {
 }
 
 ///
-/// JSON.ARRPOP <key> [path [index]]
+/// JSON.ARRPOP <key> [path [index] [COUNT n]]
+///
+/// With COUNT, pops up to n elements and replies with an array of them: from index
+/// towards the end of the array, or towards its start when index is negative or
+/// isn't given, so `COUNT n` pops the last n elements, last first
 ///
 fn json_arr_pop(ctx: &Context, args: Vec<String>) -> RedisResult {
     let mut args = args.into_iter().skip(1);
 
     let key = args.next_string()?;
 
-    let (path, index) = args
+    let path = args
         .next()
-        .map(|p| {
-            let path = backwards_compat_path(p);
-            let index = args.next_i64().unwrap_or(i64::MAX);
-            (path, index)
-        })
-        .unwrap_or(("$".to_string(), i64::MAX));
+        .map_or_else(|| "$".to_string(), backwards_compat_path);
+
+    // COUNT is only looked for after the path and the index, so legacy calls
+    // like `JSON.ARRPOP key count 3` still pop from the `count` path
+    let mut index = i64::MAX;
+    let mut arg = args.next();
+    if let Some(i) = arg.as_ref().filter(|a| a.to_uppercase() != "COUNT") {
+        index = i.parse()?;
+        arg = args.next();
+    }
+    let count = match arg {
+        Some(option) if option.to_uppercase() == "COUNT" => match args.next_i64()? {
+            n if n > 0 => Some(n as usize),
+            _ => return Err("ERR count must be a positive integer".into()),
+        },
+        Some(_) => return Err("ERR syntax error".into()),
+        None => None,
+    };
+    args.done()?;
 
     let key = ctx.open_key_writable(&key);
-    let mut res = Value::Null;
+    let doc = key
+        .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)?;
 
-    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
-        .ok_or_else(RedisError::nonexistent_key)
-        .and_then(|doc| {
-            doc.value_op(&path, |value| do_json_arr_pop(index, &mut res, value))
-                .map_err(|e| e.into())
-        })?;
-    Ok(RedisJSON::serialize(&res, Format::JSON)?.into())
+    match count {
+        Some(count) => {
+            let mut res = vec![];
+            doc.value_op(&path, |value| {
+                do_json_arr_pop_count(index, count, &mut res, value)
+            })?;
+            serialize_values(&res)
+        }
+        None => {
+            let mut res = Value::Null;
+            doc.value_op(&path, |value| do_json_arr_pop(index, &mut res, value))?;
+            Ok(RedisJSON::serialize(&res, Format::JSON)?.into())
+        }
+    }
 }
 
-fn do_json_arr_pop(mut index: i64, res: &mut Value, value: &Value) -> Result<Value, Error> {
+fn do_json_arr_pop(index: i64, res: &mut Value, value: &Value) -> Result<Value, Error> {
     value
         .as_array()
         .ok_or_else(|| err_json(value, "array"))
         .and_then(|curr| {
-            let len = curr.len() as i64;
-
-            index = index.min(len - 1);
+            let mut new_value = curr.to_owned();
+            *res = new_value.remove(pop_index(index, curr.len())?);
+            Ok(Value::Array(new_value))
+        })
+}
 
-            if index < 0 {
-                index = len + index;
+fn do_json_arr_pop_count(
+    index: i64,
+    count: usize,
+    res: &mut Vec<Value>,
+    value: &Value,
+) -> Result<Value, Error> {
+    value
+        .as_array()
+        .ok_or_else(|| err_json(value, "array"))
+        .and_then(|curr| {
+            let mut new_value = curr.to_owned();
+            let len = new_value.len();
+            if len == 0 {
+                res.clear();
+                return Ok(Value::Array(new_value));
             }
 
-            if index >= len || index < 0 {
-                return Err("ERR index out of bounds".into());
-            }
+            let start = pop_index(index, len)?;
+            *res = if (0..len as i64).contains(&index) {
+                new_value.drain(start..len.min(start + count)).collect()
+            } else {
+                let from = (start + 1).saturating_sub(count);
+                new_value.drain(from..=start).rev().collect()
+            };
+            Ok(Value::Array(new_value))
+        })
+}
+
+fn pop_index(mut index: i64, len: usize) -> Result<usize, Error> {
+    let len = len as i64;
+
+    index = index.min(len - 1);
+
+    if index < 0 {
+        index = len + index;
+    }
+
+    if index >= len || index < 0 {
+        return Err("ERR index out of bounds".into());
+    }
+
+    Ok(index as usize)
+}
+
+///
+/// JSON.ARRSPLICE <key> <path> <start> <deletecount> [json ...]
+///
+/// Like JavaScript's `Array.prototype.splice`: a negative start counts from the end,
+/// and the reply is an array of the removed elements
+///
+fn json_arr_splice(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let start = args.next_i64()?;
+    let delete_count = args.next_i64()?;
+
+    let redis_key = ctx.open_key_writable(&key);
+    let doc = redis_key
+        .get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)?;
+
+    let mut res = vec![];
+    doc.value_op(&path, |value| {
+        do_json_arr_splice(args.clone(), start, delete_count, &mut res, value)
+    })?;
+    blocking::signal_key_ready(ctx, &key);
+
+    serialize_values(&res)
+}
+
+fn do_json_arr_splice<I>(
+    args: I,
+    start: i64,
+    delete_count: i64,
+    res: &mut Vec<Value>,
+    value: &Value,
+) -> Result<Value, Error>
+where
+    I: Iterator<Item = String>,
+{
+    value
+        .as_array()
+        .ok_or_else(|| err_json(value, "array"))
+        .and_then(|curr| {
+            let len = curr.len() as i64;
+
+            let start = if start < 0 {
+                (len + start).max(0)
+            } else {
+                start.min(len)
+            };
+            let end = start + delete_count.max(0).min(len - start);
+
+            let items: Vec<Value> = args
+                .map(|json| RedisJSON::parse_str(&json, Format::JSON))
+                .collect::<Result<_, _>>()?;
 
             let mut new_value = curr.to_owned();
-            *res = new_value.remove(index as usize);
+            *res = new_value
+                .splice(start as usize..end as usize, items.into_iter())
+                .collect();
             Ok(Value::Array(new_value))
         })
 }
 
+fn serialize_values(values: &[Value]) -> RedisResult {
+    let res = values
+        .iter()
+        .map(|v| RedisJSON::serialize(v, Format::JSON))
+        .collect::<Result<Vec<String>, Error>>()?;
+    Ok(res.into())
+}
+
 ///
 /// JSON.ARRTRIM <key> <path> <start> <stop>
 ///
@@ -624,6 +1513,108 @@ fn do_json_arr_trim(start: i64, stop: i64, value: &Value) -> Result<Value, Error
         })
 }
 
+///
+/// JSON.ARRSORT <key> <path> [BY <relative-path>] [ASC|DESC] [ALPHA|NUMERIC]
+///
+/// By default mixed types are ordered as defined by `RedisJSON::cmp_values`.
+/// ALPHA compares strings as is and any other value by its JSON text.
+/// NUMERIC requires every sort key to be a number.
+///
+fn json_arr_sort(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+
+    let mut by = None;
+    let mut order = SortOrder::Asc;
+    let mut sort_type = SortType::Natural;
+
+    while let Some(s) = args.next() {
+        match s.to_uppercase().as_str() {
+            "BY" => by = Some(backwards_compat_path(args.next_string()?)),
+            "ASC" => order = SortOrder::Asc,
+            "DESC" => order = SortOrder::Desc,
+            "ALPHA" => sort_type = SortType::Alpha,
+            "NUMERIC" => sort_type = SortType::Numeric,
+            _ => return Err("ERR syntax error".into()),
+        };
+    }
+
+    let key = ctx.open_key_writable(&key);
+
+    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)
+        .and_then(|doc| {
+            doc.value_op(&path, |value| {
+                do_json_arr_sort(&by, &order, &sort_type, value)
+            })
+            .map(|v| v.as_array().map_or(usize::MAX, |v| v.len()).into())
+            .map_err(|e| e.into())
+        })
+}
+
+fn do_json_arr_sort(
+    by: &Option<String>,
+    order: &SortOrder,
+    sort_type: &SortType,
+    value: &Value,
+) -> Result<Value, Error> {
+    value
+        .as_array()
+        .ok_or_else(|| err_json(value, "array"))
+        .and_then(|curr| {
+            // Resolve the sort key of every element once, missing keys sort as null
+            let keys = curr
+                .iter()
+                .map(|v| match by {
+                    Some(by) => Ok(jsonpath_lib::select(v, by)?
+                        .first()
+                        .copied()
+                        .unwrap_or(&Value::Null)),
+                    None => Ok(v),
+                })
+                .collect::<Result<Vec<&Value>, Error>>()?;
+
+            let directed = |o: Ordering| match order {
+                SortOrder::Asc => o,
+                SortOrder::Desc => o.reverse(),
+            };
+
+            // sort_by is stable, so elements with equal keys keep their relative order
+            let mut indices: Vec<usize> = (0..curr.len()).collect();
+            match sort_type {
+                SortType::Natural => {
+                    indices.sort_by(|a, b| directed(RedisJSON::cmp_values(keys[*a], keys[*b])))
+                }
+                SortType::Alpha => {
+                    let keys: Vec<String> = keys
+                        .iter()
+                        .map(|k| match k {
+                            Value::String(s) => s.clone(),
+                            k => k.to_string(),
+                        })
+                        .collect();
+                    indices.sort_by(|a, b| directed(keys[*a].cmp(&keys[*b])));
+                }
+                SortType::Numeric => {
+                    let keys = keys
+                        .iter()
+                        .map(|k| match k {
+                            Value::Number(n) => Ok(n),
+                            k => Err(err_json(k, "number")),
+                        })
+                        .collect::<Result<Vec<&Number>, Error>>()?;
+                    indices.sort_by(|a, b| directed(RedisJSON::cmp_numbers(keys[*a], keys[*b])));
+                }
+            }
+
+            Ok(Value::Array(
+                indices.into_iter().map(|i| curr[i].clone()).collect(),
+            ))
+        })
+}
+
 ///
 /// JSON.OBJKEYS <key> [path]
 ///
@@ -642,6 +1633,94 @@ fn json_obj_keys(ctx: &Context, args: Vec<String>) -> RedisResult {
     Ok(value)
 }
 
+///
+/// JSON.OBJSCAN <key> <path> <cursor> [MATCH pattern] [COUNT n] [WITHVALUES]
+///
+/// Replies with the cursor to continue from, 0 when the scan is over, and the names of
+/// the scanned object members, followed each by its JSON value with WITHVALUES
+///
+fn json_obj_scan(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let cursor = args
+        .next_string()?
+        .parse::<u64>()
+        .map_err(|_| RedisError::from("ERR invalid cursor"))?;
+
+    let mut pattern = None;
+    let mut count = 10;
+    let mut with_values = false;
+    while let Some(arg) = args.next() {
+        match arg.to_uppercase().as_str() {
+            "MATCH" => pattern = Some(args.next_string()?),
+            "COUNT" => match args.next_i64()? {
+                n if n > 0 => count = n as usize,
+                _ => return Err("ERR count must be a positive integer".into()),
+            },
+            "WITHVALUES" => with_values = true,
+            _ => return Err("ERR syntax error".into()),
+        }
+    }
+
+    let key = ctx.open_key(&key);
+
+    let doc = match key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)? {
+        Some(doc) => doc,
+        None => {
+            return Ok(RedisValue::Array(vec![
+                RedisValue::BulkString("0".to_string()),
+                RedisValue::Array(vec![]),
+            ]))
+        }
+    };
+    let (cursor, members) = doc.obj_scan(&path, cursor, pattern.as_deref(), count)?;
+
+    let mut res = vec![];
+    for (name, value) in members {
+        res.push(RedisValue::BulkString(name.to_string()));
+        if with_values {
+            res.push(RedisValue::BulkString(RedisJSON::serialize(
+                value,
+                Format::JSON,
+            )?));
+        }
+    }
+    Ok(RedisValue::Array(vec![
+        RedisValue::BulkString(cursor.to_string()),
+        RedisValue::Array(res),
+    ]))
+}
+
+///
+/// JSON.RENAME <key> <path> <new-name> [NX]
+///
+/// Renames the object members at path, keeping their position, and replies with
+/// their count. With NX a member isn't renamed when its object already has the new name.
+/// Without NX, a path matching many members of the same object is an error.
+///
+fn json_rename(ctx: &Context, args: Vec<String>) -> RedisResult {
+    let mut args = args.into_iter().skip(1);
+    let key = args.next_string()?;
+    let path = backwards_compat_path(args.next_string()?);
+    let new_name = args.next_string()?;
+
+    let mut nx = false;
+    if let Some(s) = args.next() {
+        match s.to_uppercase().as_str() {
+            "NX" => nx = true,
+            _ => return Err("ERR syntax error".into()),
+        };
+    }
+    args.done()?;
+
+    let key = ctx.open_key_writable(&key);
+
+    key.get_value::<RedisJSON>(&REDIS_JSON_TYPE)?
+        .ok_or_else(RedisError::nonexistent_key)
+        .and_then(|doc| Ok(doc.rename_member(&path, &new_name, nx)?.into()))
+}
+
 ///
 /// JSON.OBJLEN <key> [path]
 ///
@@ -706,7 +1785,8 @@ fn resp_serialize(doc: &Value) -> RedisValue {
         Value::Number(n) => n
             .as_i64()
             .map(|i| RedisValue::Integer(i))
-            .unwrap_or_else(|| RedisValue::Float(n.as_f64().unwrap())),
+            .or_else(|| n.as_f64().map(RedisValue::Float))
+            .unwrap_or_else(|| RedisValue::BulkString(n.to_string())),
 
         Value::String(s) => RedisValue::BulkString(s.clone()),
 
@@ -758,6 +1838,12 @@ fn json_cache_init(_ctx: &Context, _args: Vec<String>) -> RedisResult {
 
 pub extern "C" fn init(raw_ctx: *mut rawmod::RedisModuleCtx) -> c_int {
     crate::commands::index::schema_map::init();
+
+    let res = blocking::register(raw_ctx);
+    if res != rawmod::Status::Ok as c_int {
+        return res;
+    }
+
     redisearch_api::init(raw_ctx)
 }
 
@@ -774,19 +1860,36 @@ redis_module! {
         ["json.mget", json_mget, ""],
         ["json.set", json_set, "write deny-oom"],
         ["json.type", json_type, ""],
+        ["json.hash", json_hash, ""],
+        ["json.exists", json_exists, ""],
+        ["json.paths", json_paths, ""],
+        ["json.equals", json_equals, ""],
+        ["json.contains", json_contains, ""],
         ["json.numincrby", json_num_incrby, "write"],
         ["json.nummultby", json_num_multby, "write"],
         ["json.numpowby", json_num_powby, "write"],
+        ["json.nummaxby", json_num_maxby, "write"],
+        ["json.numminby", json_num_minby, "write"],
         ["json.strappend", json_str_append, "write deny-oom"],
+        ["json.strprepend", json_str_prepend, "write deny-oom"],
+        ["json.strsetrange", json_str_set_range, "write deny-oom"],
         ["json.strlen", json_str_len, ""],
+        ["json.strrange", json_str_range, ""],
         ["json.arrappend", json_arr_append, "write deny-oom"],
+        ["json.arraddunique", json_arr_add_unique, "write deny-oom"],
+        ["json.arrrem", json_arr_rem, "write"],
         ["json.arrindex", json_arr_index, ""],
         ["json.arrinsert", json_arr_insert, "write deny-oom"],
         ["json.arrlen", json_arr_len, ""],
         ["json.arrpop", json_arr_pop, "write"],
+        ["json.arrsplice", json_arr_splice, "write deny-oom"],
+        ["json.arrslice", json_arr_slice, ""],
         ["json.arrtrim", json_arr_trim, "write"],
+        ["json.arrsort", json_arr_sort, "write"],
         ["json.objkeys", json_obj_keys, ""],
+        ["json.objscan", json_obj_scan, ""],
         ["json.objlen", json_obj_len, ""],
+        ["json.rename", json_rename, "write"],
         ["json.debug", json_debug, ""],
         ["json.forget", json_del, "write"],
         ["json.resp", json_resp, ""],
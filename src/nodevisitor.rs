@@ -1,48 +1,85 @@
 use jsonpath_lib::parser::{NodeVisitor, ParseToken};
 use jsonpath_lib::Parser;
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
-enum VisitStatus {
-    NotValid,
-    PartialValid,
-    Valid,
+///
+/// A single step of a static path: an object member or an array element
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum PathSegment {
+    Key(String),
+    Index(i64),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) => {
+                write!(f, "['{}']", key.replace('\\', "\\\\").replace('\'', "\\'"))
+            }
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
 }
 
 pub struct NodeVisitorImpl {
-    valid: VisitStatus,
     last_token: Option<ParseToken>,
+    segments: Option<Vec<PathSegment>>,
 }
 
 impl NodeVisitorImpl {
     ///
-    /// Checks if path is static & valid
+    /// Returns the segments of a static path, or None if the path isn't static.
+    /// Keys can use the dot or the bracket notation and indices can be negative,
+    /// like `$.a["b"][-1]`.
     ///
-    pub fn check(input: &str) -> Result<bool, String> {
+    pub fn segments(input: &str) -> Result<Option<Vec<PathSegment>>, String> {
         let node = Parser::compile(input)?;
         let mut visitor = NodeVisitorImpl {
-            valid: VisitStatus::PartialValid,
             last_token: None,
+            segments: Some(vec![]),
         };
         visitor.visit(&node);
-        Ok(visitor.valid == VisitStatus::Valid)
+        Ok(match visitor.last_token {
+            Some(ParseToken::Absolute) | Some(ParseToken::Key(_)) | Some(ParseToken::ArrayEof) => {
+                visitor.segments
+            }
+            _ => None,
+        })
+    }
+
+    fn visit_segment(&mut self, token: &ParseToken) {
+        let segment = match (&self.last_token, token) {
+            (None, ParseToken::Absolute) => None,
+            (Some(ParseToken::Absolute), ParseToken::In) => None,
+            (Some(ParseToken::Absolute), ParseToken::Array) => None,
+            (Some(ParseToken::In), ParseToken::Key(key)) => Some(PathSegment::Key(key.clone())),
+            (Some(ParseToken::Key(_)), ParseToken::In) => None,
+            (Some(ParseToken::Key(_)), ParseToken::Array) => None,
+            (Some(ParseToken::Key(_)), ParseToken::ArrayEof) => None,
+            (Some(ParseToken::Array), ParseToken::Key(key)) => Some(PathSegment::Key(key.clone())),
+            (Some(ParseToken::Array), ParseToken::Number(n)) if n.fract() == 0.0 => {
+                Some(PathSegment::Index(*n as i64))
+            }
+            (Some(ParseToken::Number(_)), ParseToken::ArrayEof) => None,
+            (Some(ParseToken::ArrayEof), ParseToken::In) => None,
+            (Some(ParseToken::ArrayEof), ParseToken::Array) => None,
+            _ => {
+                self.segments = None;
+                return;
+            }
+        };
+        if let (Some(segments), Some(segment)) = (&mut self.segments, segment) {
+            segments.push(segment);
+        }
     }
 }
 
 impl NodeVisitor for NodeVisitorImpl {
     fn visit_token(&mut self, token: &ParseToken) {
-        if self.valid != VisitStatus::NotValid {
-            self.valid = match (&self.last_token, token) {
-                (None, ParseToken::Absolute) => VisitStatus::PartialValid,
-                (Some(ParseToken::Absolute), ParseToken::In) => VisitStatus::PartialValid,
-                (Some(ParseToken::In), ParseToken::Key(_)) => VisitStatus::Valid,
-                (Some(ParseToken::Key(_)), ParseToken::In) => VisitStatus::PartialValid,
-                (Some(ParseToken::Key(_)), ParseToken::Array) => VisitStatus::PartialValid,
-                (Some(ParseToken::Array), ParseToken::Number(_)) => VisitStatus::PartialValid,
-                (Some(ParseToken::Number(_)), ParseToken::ArrayEof) => VisitStatus::PartialValid,
-                (Some(ParseToken::ArrayEof), ParseToken::In) => VisitStatus::PartialValid,
-                _ => VisitStatus::NotValid,
-            };
-            self.last_token = Some(token.clone());
+        if self.segments.is_some() {
+            self.visit_segment(token);
         }
+        self.last_token = Some(token.clone());
     }
 }
@@ -4,17 +4,28 @@
 // User-provided JSON is converted to a tree. This tree is stored transparently in Redis.
 // It can be operated on (e.g. INCR) and serialized back to JSON.
 
+use crate::array_index::ArrayIndex;
 use crate::backward;
+use crate::canonical;
 use crate::error::Error;
-use crate::nodevisitor::NodeVisitorImpl;
+use crate::nodevisitor::{NodeVisitorImpl, PathSegment};
 
+use bigdecimal::BigDecimal;
 use bson::decode_document;
 use jsonpath_lib::SelectorMut;
 use redis_module::raw;
-use serde_json::Value;
+use serde_json::{Map, Number, Value};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
 use std::mem;
 use std::os::raw::{c_int, c_void};
+use std::str::FromStr;
+
+/// Numbers with more digits, or a larger scale, than this are compared and computed
+/// as doubles: BigDecimal's cost grows with the square of the digits it has to align
+const MAX_DECIMAL_DIGITS: i64 = 1000;
 
 #[derive(Debug, PartialEq)]
 pub enum SetOptions {
@@ -38,12 +49,43 @@ impl Format {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum StrUnit {
+    Bytes,
+    Chars,
+    Utf16,
+}
+impl StrUnit {
+    pub fn from_str(s: &str) -> Result<StrUnit, Error> {
+        match s {
+            "BYTES" => Ok(StrUnit::Bytes),
+            "CHARS" => Ok(StrUnit::Chars),
+            "UTF16" => Ok(StrUnit::Utf16),
+            _ => return Err("ERR wrong unit".into()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SortType {
+    Natural,
+    Alpha,
+    Numeric,
+}
+
 ///
 /// Backwards compatibility convertor for RedisJSON 1.x clients
 ///
 pub struct Path {
     pub path: String,
     pub fixed: String,
+    pub default: Option<Value>,
 }
 
 impl Path {
@@ -58,7 +100,11 @@ impl Path {
                 fixed.insert_str(0, "$.");
             }
         }
-        Path { path, fixed }
+        Path {
+            path,
+            fixed,
+            default: None,
+        }
     }
 }
 
@@ -66,12 +112,19 @@ impl Path {
 pub struct RedisJSON {
     data: Value,
     pub index: Option<String>,
+    /// The member names of the objects being scanned by JSON.OBJSCAN, sorted by hash
+    /// and keyed by path
+    scan_indexes: RefCell<HashMap<String, Vec<(u64, String)>>>,
 }
 
 impl RedisJSON {
     pub fn parse_str(data: &str, format: Format) -> Result<Value, Error> {
         match format {
-            Format::JSON => Ok(serde_json::from_str(data)?),
+            Format::JSON => {
+                let value = serde_json::from_str(data)?;
+                RedisJSON::check_numbers(&value)?;
+                Ok(value)
+            }
             Format::BSON => decode_document(&mut Cursor::new(data.as_bytes()))
                 .map(|docs| {
                     let v = if docs.len() >= 1 {
@@ -87,59 +140,97 @@ impl RedisJSON {
         }
     }
 
+    ///
+    /// Rejects the numbers out of the double range, like 1e400: serde_json keeps their
+    /// text thanks to arbitrary_precision, but they have no value as a double
+    ///
+    fn check_numbers(value: &Value) -> Result<(), Error> {
+        match value {
+            Value::Number(n) if !RedisJSON::is_finite(n) => {
+                Err(format!("ERR number {} is out of the double range", n).into())
+            }
+            Value::Array(arr) => arr.iter().try_for_each(RedisJSON::check_numbers),
+            Value::Object(map) => map.values().try_for_each(RedisJSON::check_numbers),
+            _ => Ok(()),
+        }
+    }
+
     pub fn from_str(data: &str, index: &Option<String>, format: Format) -> Result<Self, Error> {
         let value = RedisJSON::parse_str(data, format)?;
         Ok(Self {
             data: value,
             index: index.clone(),
+            scan_indexes: RefCell::default(),
         })
     }
 
+    ///
+    /// Adds a member to an existing object, returns false when it already exists.
+    /// Like with MKPATH, the errors name the segment that can't be created.
+    ///
     fn add_value(&mut self, path: &str, value: Value) -> Result<bool, Error> {
-        if NodeVisitorImpl::check(path)? {
-            let mut splits = path.rsplitn(2, '.');
-            let key = splits.next().unwrap();
-            let prefix = splits.next().unwrap();
-
-            let mut current_data = self.data.take();
-            if prefix == "$" {
-                let res = if let Value::Object(ref mut map) = current_data {
-                    if map.contains_key(key) {
-                        false
-                    } else {
-                        map.insert(key.to_string(), value.clone());
-                        true
-                    }
-                } else {
-                    false
-                };
-                self.data = current_data;
-                Ok(res)
-            } else {
-                let mut set = false;
-                self.data = jsonpath_lib::replace_with(current_data, prefix, &mut |mut ret| {
-                    if let Value::Object(ref mut map) = ret {
-                        if map.contains_key(key) {
-                            set = false;
-                        } else {
-                            map.insert(key.to_string(), value.clone());
-                            set = true;
-                        }
-                    }
-                    Some(ret)
-                })?;
-                Ok(set)
+        let mut segments = NodeVisitorImpl::segments(path)?.unwrap_or_default();
+        if let Some(PathSegment::Key(key)) = segments.pop() {
+            let parent_path = RedisJSON::normalized_path(&segments);
+            match self.value_at_mut(&segments) {
+                Some(Value::Object(map)) if map.contains_key(&key) => Ok(false),
+                Some(Value::Object(map)) => {
+                    map.insert(key, value);
+                    Ok(true)
+                }
+                Some(v) => Err(format!(
+                    "ERR can't create {}{} - expected object at {} but found {}",
+                    parent_path,
+                    PathSegment::Key(key),
+                    parent_path,
+                    RedisJSON::value_name(v)
+                )
+                .into()),
+                None => {
+                    let missing = (1..segments.len())
+                        .find(|&i| self.value_at_mut(&segments[..i]).is_none())
+                        .unwrap_or(segments.len());
+                    Err(format!(
+                        "ERR {} doesn't exist - use MKPATH to create it",
+                        RedisJSON::normalized_path(&segments[..missing])
+                    )
+                    .into())
+                }
             }
         } else {
             Err("Err: wrong static path".into())
         }
     }
 
+    ///
+    /// Returns the value at static path segments. Negative indices count from the end
+    /// of arrays and, like in reads, the ones before the start select the first element.
+    ///
+    fn value_at_mut(&mut self, segments: &[PathSegment]) -> Option<&mut Value> {
+        segments
+            .iter()
+            .try_fold(&mut self.data, |current, segment| {
+                match (current, segment) {
+                    (Value::Object(map), PathSegment::Key(key)) => map.get_mut(key),
+                    (Value::Array(arr), PathSegment::Index(index)) => {
+                        let len = arr.len() as i64;
+                        if len > 0 && *index < len {
+                            Some(&mut arr[index.normalize(len)])
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None,
+                }
+            })
+    }
+
     pub fn set_value(
         &mut self,
         data: &str,
         path: &str,
         option: &SetOptions,
+        mkpath: bool,
         format: Format,
     ) -> Result<bool, Error> {
         let json: Value = RedisJSON::parse_str(data, format)?;
@@ -153,37 +244,281 @@ impl RedisJSON {
         } else {
             let mut replaced = false;
             if SetOptions::NotExists != *option {
-                let current_data = self.data.take();
-                self.data = jsonpath_lib::replace_with(current_data, path, &mut |_v| {
+                // Work on a copy, so an error leaves the document untouched
+                self.data = jsonpath_lib::replace_with(self.data.clone(), path, &mut |_v| {
                     replaced = true;
                     Some(json.clone())
                 })?;
             }
             if replaced {
                 Ok(true)
-            } else if SetOptions::AlreadyExists != *option {
-                self.add_value(path, json)
-            } else {
+            } else if SetOptions::AlreadyExists == *option {
                 Ok(false)
+            } else if mkpath {
+                self.create_value(path, json)
+            } else {
+                self.add_value(path, json)
             }
         }
     }
 
+    ///
+    /// Replaces every value matched by path and returns their count
+    ///
+    pub fn update_values(
+        &mut self,
+        data: &str,
+        path: &str,
+        format: Format,
+    ) -> Result<usize, Error> {
+        let json: Value = RedisJSON::parse_str(data, format)?;
+        let mut updated = 0;
+        self.data = jsonpath_lib::replace_with(self.data.clone(), path, &mut |_v| {
+            updated += 1;
+            Some(json.clone())
+        })?;
+        Ok(updated)
+    }
+
+    pub fn is_static_path(path: &str) -> Result<bool, Error> {
+        Ok(NodeVisitorImpl::segments(path)?.is_some())
+    }
+
+    ///
+    /// Sets a missing value at a static path, creating its missing parents: objects
+    /// for keys and arrays for index 0. An array element can only be created at the
+    /// end of the array. Returns false when the path already exists.
+    ///
+    pub fn create_value(&mut self, path: &str, value: Value) -> Result<bool, Error> {
+        let mut segments = NodeVisitorImpl::segments(path)?
+            .ok_or_else(|| Error::from("ERR wrong static path"))?
+            .into_iter();
+
+        let mut parent_path = String::from("$");
+        let mut current = &mut self.data;
+        while let Some(segment) = segments.next() {
+            let segment_path = format!("{}{}", parent_path, segment);
+            current = match (current, segment) {
+                (Value::Object(map), PathSegment::Key(key)) => {
+                    if !map.contains_key(&key) {
+                        // Build the whole missing part before inserting it,
+                        // so an invalid path leaves the document untouched
+                        let value =
+                            RedisJSON::build_value(&segment_path, segments.collect(), value)?;
+                        map.insert(key, value);
+                        return Ok(true);
+                    }
+                    map.get_mut(&key).unwrap()
+                }
+                (Value::Array(arr), PathSegment::Index(index)) => {
+                    let len = arr.len() as i64;
+                    if index == len {
+                        let value =
+                            RedisJSON::build_value(&segment_path, segments.collect(), value)?;
+                        arr.push(value);
+                        return Ok(true);
+                    }
+                    if len == 0 || index > len {
+                        return Err(format!(
+                            "ERR can't create {} - array index out of range",
+                            segment_path
+                        )
+                        .into());
+                    }
+                    &mut arr[index.normalize(len)]
+                }
+                (v, segment) => {
+                    let expected = match segment {
+                        PathSegment::Key(_) => "object",
+                        PathSegment::Index(_) => "array",
+                    };
+                    return Err(format!(
+                        "ERR can't create {} - expected {} at {} but found {}",
+                        segment_path,
+                        expected,
+                        parent_path,
+                        RedisJSON::value_name(v)
+                    )
+                    .into());
+                }
+            };
+            parent_path = segment_path;
+        }
+        Ok(false)
+    }
+
+    fn build_value(path: &str, segments: Vec<PathSegment>, value: Value) -> Result<Value, Error> {
+        let mut value = value;
+        for (i, segment) in segments.iter().enumerate().rev() {
+            value = match segment {
+                PathSegment::Key(key) => {
+                    let mut map = Map::new();
+                    map.insert(key.to_string(), value);
+                    Value::Object(map)
+                }
+                PathSegment::Index(0) => Value::Array(vec![value]),
+                PathSegment::Index(_) => {
+                    let segment_path = segments[..=i]
+                        .iter()
+                        .fold(path.to_string(), |acc, s| format!("{}{}", acc, s));
+                    return Err(format!(
+                        "ERR can't create {} - array index out of range",
+                        segment_path
+                    )
+                    .into());
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    ///
+    /// Deletes the values matched by path and returns their count. A matched value
+    /// inside another one is deleted with it, and isn't counted on its own.
+    ///
     pub fn delete_path(&mut self, path: &str) -> Result<usize, Error> {
-        let current_data = self.data.take();
+        let mut paths = self.match_paths(path)?;
+        // The root can't be removed from the document
+        paths.retain(|path| !path.is_empty());
+        // Paths are in document order, so a value's descendants follow it
+        paths.dedup_by(|descendant, ancestor| descendant.starts_with(ancestor));
+
+        // Delete from the end, so removing an array element doesn't shift the
+        // indices of the ones still to delete
+        for path in paths.iter().rev() {
+            let (last, parent) = path.split_last().unwrap();
+            match (self.value_at_mut(parent), last) {
+                (Some(Value::Object(map)), PathSegment::Key(key)) => {
+                    map.shift_remove(key);
+                }
+                (Some(Value::Array(arr)), PathSegment::Index(index)) => {
+                    arr.remove(*index as usize);
+                }
+                _ => {}
+            }
+        }
+        Ok(paths.len())
+    }
 
-        let mut deleted = 0;
-        self.data = jsonpath_lib::replace_with(current_data, path, &mut |v| {
-            if !v.is_null() {
-                deleted = deleted + 1; // might delete more than a single value
+    ///
+    /// Renames the object members matched by path, keeping their position, and returns
+    /// their count. A member with the new name is replaced, or with `nx` the matched one
+    /// isn't renamed and isn't counted. Without `nx`, matching many members of the same
+    /// object is an error, since only one of them could keep the new name.
+    ///
+    pub fn rename_member(&mut self, path: &str, new_name: &str, nx: bool) -> Result<usize, Error> {
+        let paths = self.match_paths(path)?;
+        if !nx {
+            let mut parents = HashSet::new();
+            let shared_parent = paths
+                .iter()
+                .filter_map(|path| match path.split_last() {
+                    Some((PathSegment::Key(_), parent)) => Some(parent),
+                    _ => None,
+                })
+                .any(|parent| !parents.insert(parent));
+            if shared_parent {
+                return Err("ERR can't rename many members of the same object to one name".into());
             }
-            None
-        })?;
-        Ok(deleted)
+        }
+
+        let mut renamed = 0;
+        // From the end, so renaming a member doesn't change the paths still to rename
+        for path in paths.iter().rev() {
+            if let Some((PathSegment::Key(name), parent)) = path.split_last() {
+                if let Some(Value::Object(map)) = self.value_at_mut(parent) {
+                    if name != new_name {
+                        if nx && map.contains_key(new_name) {
+                            continue;
+                        }
+                        map.shift_remove(new_name);
+                        if let Some(index) = map.keys().position(|k| k == name) {
+                            let value = map.shift_remove(name).unwrap();
+                            map.shift_insert(index, new_name.to_string(), value);
+                        }
+                    }
+                    renamed += 1;
+                }
+            }
+        }
+        Ok(renamed)
+    }
+
+    ///
+    /// Returns the normalized paths of the values matched by path, like `$['items'][3]['id']`
+    ///
+    pub fn normalized_paths(&self, path: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .match_paths(path)?
+            .iter()
+            .map(|segments| RedisJSON::normalized_path(segments))
+            .collect())
+    }
+
+    fn normalized_path(segments: &[PathSegment]) -> String {
+        segments
+            .iter()
+            .fold(String::from("$"), |acc, s| format!("{}{}", acc, s))
+    }
+
+    ///
+    /// Returns the number of values matched by path, each counted once like in
+    /// normalized_paths
+    ///
+    pub fn match_count(&self, path: &str) -> Result<usize, Error> {
+        Ok(self.match_paths(path)?.len())
+    }
+
+    ///
+    /// Returns the paths of the values matched by path, in document order
+    ///
+    fn match_paths(&self, path: &str) -> Result<Vec<Vec<PathSegment>>, Error> {
+        let matches: HashSet<*const Value> = self
+            .get_values(path)?
+            .into_iter()
+            .map(|v| v as *const Value)
+            .collect();
+
+        let mut paths = vec![];
+        RedisJSON::find_paths(&self.data, &matches, &mut vec![], &mut paths);
+        Ok(paths)
     }
 
-    pub fn to_string(&self, path: &str, format: Format) -> Result<String, Error> {
-        let results = self.get_first(path)?;
+    fn find_paths(
+        value: &Value,
+        matches: &HashSet<*const Value>,
+        path: &mut Vec<PathSegment>,
+        paths: &mut Vec<Vec<PathSegment>>,
+    ) {
+        if matches.contains(&(value as *const Value)) {
+            paths.push(path.clone());
+        }
+        match value {
+            Value::Array(arr) => {
+                for (i, v) in arr.iter().enumerate() {
+                    path.push(PathSegment::Index(i as i64));
+                    RedisJSON::find_paths(v, matches, path, paths);
+                    path.pop();
+                }
+            }
+            Value::Object(map) => {
+                for (k, v) in map {
+                    path.push(PathSegment::Key(k.to_string()));
+                    RedisJSON::find_paths(v, matches, path, paths);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn to_string(
+        &self,
+        path: &str,
+        default: &Option<Value>,
+        format: Format,
+    ) -> Result<String, Error> {
+        let results = self.get_first_or(path, default)?;
         Self::serialize(results, format)
     }
 
@@ -200,13 +535,11 @@ impl RedisJSON {
     pub fn to_json(&self, paths: &mut Vec<Path>) -> Result<String, Error> {
         let mut selector = jsonpath_lib::selector(&self.data);
         let mut result = paths.drain(..).fold(String::from("{"), |mut acc, path| {
-            let value = match selector(&path.fixed) {
-                Ok(s) => match s.first() {
-                    Some(v) => v,
-                    None => &Value::Null,
-                },
-                Err(_) => &Value::Null,
-            };
+            let value = selector(&path.fixed)
+                .ok()
+                .and_then(|s| s.first().copied())
+                .or_else(|| path.default.as_ref())
+                .unwrap_or(&Value::Null);
             acc.push('\"');
             acc.push_str(&path.path);
             acc.push_str("\":");
@@ -221,11 +554,202 @@ impl RedisJSON {
         Ok(result.into())
     }
 
-    pub fn str_len(&self, path: &str) -> Result<usize, Error> {
+    pub fn to_canonical(&self, paths: &mut Vec<Path>) -> Result<String, Error> {
+        canonical::to_string(&self.get_results(paths, None)?)
+    }
+
+    ///
+    /// Returns the value of the path, or an object of the values of each path when
+    /// there are many, with the containers deeper than max_depth truncated
+    ///
+    pub fn get_results(
+        &self,
+        paths: &mut Vec<Path>,
+        max_depth: Option<usize>,
+    ) -> Result<Value, Error> {
+        let truncate = |value: &Value| match max_depth {
+            Some(max_depth) => RedisJSON::truncate(value, max_depth),
+            None => value.clone(),
+        };
+        if paths.len() == 1 {
+            return Ok(truncate(
+                self.get_first_or(&paths[0].fixed, &paths[0].default)?,
+            ));
+        }
+
+        let mut selector = jsonpath_lib::selector(&self.data);
+        let results = paths
+            .drain(..)
+            .map(|path| {
+                let value = selector(&path.fixed)
+                    .ok()
+                    .and_then(|s| s.first().map(|v| truncate(v)))
+                    .or_else(|| path.default.as_ref().map(&truncate))
+                    .unwrap_or(Value::Null);
+                (path.path, value)
+            })
+            .collect();
+        Ok(Value::Object(results))
+    }
+
+    ///
+    /// Replaces the containers deeper than max_depth below value with a stub holding
+    /// their type and size, like `{"$truncated":"object","size":1234}`
+    ///
+    pub fn truncate(value: &Value, max_depth: usize) -> Value {
+        RedisJSON::truncate_at(value, 0, max_depth)
+    }
+
+    fn truncate_at(value: &Value, depth: usize, max_depth: usize) -> Value {
+        match value {
+            Value::Array(arr) if depth > max_depth => RedisJSON::truncated("array", arr.len()),
+            Value::Object(map) if depth > max_depth => RedisJSON::truncated("object", map.len()),
+            Value::Array(arr) => Value::Array(
+                arr.iter()
+                    .map(|v| RedisJSON::truncate_at(v, depth + 1, max_depth))
+                    .collect(),
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| {
+                        (
+                            k.to_string(),
+                            RedisJSON::truncate_at(v, depth + 1, max_depth),
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+
+    fn truncated(type_name: &str, size: usize) -> Value {
+        let mut stub = Map::new();
+        stub.insert("$truncated".to_string(), Value::from(type_name));
+        stub.insert("size".to_string(), Value::from(size));
+        Value::Object(stub)
+    }
+
+    ///
+    /// Returns a copy of the document without the values matched by the exclude paths,
+    /// and with only the values matched by the projections when there are any. A projected
+    /// value keeps its place in the document, or is put under its alias.
+    ///
+    pub fn project(
+        &self,
+        projections: &[(Path, Option<String>)],
+        excludes: &[Path],
+    ) -> Result<Value, Error> {
+        let excluded;
+        let doc = if excludes.is_empty() {
+            self
+        } else {
+            let mut copy = RedisJSON {
+                data: self.data.clone(),
+                index: None,
+                scan_indexes: RefCell::default(),
+            };
+            for path in excludes {
+                copy.delete_path(&path.fixed)?;
+            }
+            excluded = copy;
+            &excluded
+        };
+        if projections.is_empty() {
+            return Ok(doc.data.clone());
+        }
+
+        let mut matches = HashSet::new();
+        let mut aliases = vec![];
+        for (path, alias) in projections {
+            let values = doc.get_values(&path.fixed)?;
+            match alias {
+                Some(alias) => {
+                    if let Some(value) = values.first() {
+                        aliases.push((alias.to_string(), (*value).clone()));
+                    }
+                }
+                None => matches.extend(values.into_iter().map(|v| v as *const Value)),
+            }
+        }
+
+        let mut result =
+            RedisJSON::prune(&doc.data, &matches).unwrap_or_else(|| Value::Object(Map::new()));
+        match &mut result {
+            Value::Object(map) => map.extend(aliases),
+            _ if !aliases.is_empty() => {
+                return Err("ERR can't add an alias to a projection that isn't an object".into())
+            }
+            _ => {}
+        }
+        Ok(result)
+    }
+
+    ///
+    /// Keeps the matched values and the containers leading to them, dropping
+    /// everything else. Array elements keep their order but not their indices.
+    ///
+    fn prune(value: &Value, matches: &HashSet<*const Value>) -> Option<Value> {
+        if matches.contains(&(value as *const Value)) {
+            return Some(value.clone());
+        }
+        match value {
+            Value::Array(arr) => {
+                let arr: Vec<Value> = arr
+                    .iter()
+                    .filter_map(|v| RedisJSON::prune(v, matches))
+                    .collect();
+                if arr.is_empty() {
+                    None
+                } else {
+                    Some(Value::Array(arr))
+                }
+            }
+            Value::Object(map) => {
+                let map: Map<String, Value> = map
+                    .iter()
+                    .filter_map(|(k, v)| RedisJSON::prune(v, matches).map(|v| (k.to_string(), v)))
+                    .collect();
+                if map.is_empty() {
+                    None
+                } else {
+                    Some(Value::Object(map))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn str_len(&self, path: &str, unit: &StrUnit) -> Result<usize, Error> {
         self.get_first(path)?
             .as_str()
             .ok_or_else(|| "ERR wrong type of path value".into())
-            .map(|s| s.len())
+            .map(|s| match unit {
+                StrUnit::Bytes => s.len(),
+                StrUnit::Chars => s.chars().count(),
+                StrUnit::Utf16 => s.encode_utf16().count(),
+            })
+    }
+
+    pub fn str_range(&self, path: &str, start: i64, end: i64) -> Result<String, Error> {
+        let s = self
+            .get_first(path)?
+            .as_str()
+            .ok_or_else(|| Error::from("ERR wrong type of path value"))?;
+
+        let len = s.chars().count() as i64;
+        if len == 0 || start >= len || end < -len {
+            return Ok(String::new());
+        }
+
+        // Offsets are in characters and both ends are inclusive, like GETRANGE
+        let start = start.normalize(len);
+        let end = end.normalize(len);
+        if start > end {
+            return Ok(String::new());
+        }
+
+        Ok(s.chars().skip(start).take(end - start + 1).collect())
     }
 
     pub fn arr_len(&self, path: &str) -> Result<usize, Error> {
@@ -249,13 +773,78 @@ impl RedisJSON {
             .map(|obj| obj.keys().collect())
     }
 
+    ///
+    /// Returns up to about count members of the object at path, starting at cursor, and
+    /// the cursor to continue from, 0 when the scan is over. Members are scanned in the
+    /// order of the hash of their name, so like HSCAN a member present during the whole
+    /// scan is returned once, whatever is added or removed meanwhile. Members whose name
+    /// doesn't match the glob-style pattern are skipped after being counted.
+    ///
+    /// A scan starting at cursor 0 sorts the hashes of the names once and keeps them
+    /// until it's over, so each call only reads the count names after cursor. Members
+    /// added afterwards may be missed, and the removed ones are skipped, which HSCAN
+    /// allows as well.
+    ///
+    pub fn obj_scan<'a>(
+        &'a self,
+        path: &'a str,
+        cursor: u64,
+        pattern: Option<&str>,
+        count: usize,
+    ) -> Result<(u64, Vec<(&'a String, &'a Value)>), Error> {
+        let obj = self
+            .get_first(path)?
+            .as_object()
+            .ok_or_else(|| Error::from("ERR wrong type of path value"))?;
+
+        let mut scan_indexes = self.scan_indexes.borrow_mut();
+        if cursor == 0 || !scan_indexes.contains_key(path) {
+            let mut index: Vec<(u64, String)> = obj
+                .keys()
+                .map(|k| (twox_hash::xxh3::hash64(k.as_bytes()), k.to_string()))
+                .collect();
+            index.sort_unstable();
+            scan_indexes.insert(path.to_string(), index);
+        }
+        let index = &scan_indexes[path];
+
+        let start = index
+            .binary_search_by(|(hash, _)| match hash.cmp(&cursor) {
+                Ordering::Less => Ordering::Less,
+                _ => Ordering::Greater,
+            })
+            .unwrap_err();
+        let mut end = index.len().min(start + count.max(1));
+        // Members with the same hash can't be told apart by the cursor,
+        // so they're returned together
+        while end < index.len() && index[end].0 == index[end - 1].0 {
+            end += 1;
+        }
+        let next_cursor = index.get(end).map_or(0, |(hash, _)| *hash);
+
+        let pattern: Option<Vec<char>> = pattern.map(|p| p.chars().collect());
+        let scanned = index[start..end]
+            .iter()
+            .filter(|(_, k)| match &pattern {
+                Some(pattern) => glob_match(pattern, &k.chars().collect::<Vec<char>>()),
+                None => true,
+            })
+            .filter_map(|(_, k)| obj.get_key_value(k))
+            .collect();
+
+        if next_cursor == 0 {
+            scan_indexes.remove(path);
+        }
+        Ok((next_cursor, scanned))
+    }
+
     pub fn arr_index(&self, path: &str, scalar: &str, start: i64, end: i64) -> Result<i64, Error> {
         if let Value::Array(arr) = self.get_first(path)? {
             // end=-1/0 means INFINITY to support backward with RedisJSON
             if arr.is_empty() || end < -1 {
                 return Ok(-1);
             }
-            match serde_json::from_str(scalar)? {
+            match RedisJSON::parse_str(scalar, Format::JSON)? {
                 Value::Array(_) | Value::Object(_) => Ok(-1),
                 v => {
                     let end: usize = if end == 0 || end == -1 {
@@ -269,7 +858,7 @@ impl RedisJSON {
                         return Ok(-1);
                     }
                     let slice = &arr[start..=end];
-                    match slice.iter().position(|r| r == &v) {
+                    match slice.iter().position(|r| RedisJSON::values_equal(r, &v)) {
                         Some(i) => Ok((start + i) as i64),
                         None => Ok(-1),
                     }
@@ -280,6 +869,35 @@ impl RedisJSON {
         }
     }
 
+    pub fn arr_slice(
+        &self,
+        path: &str,
+        start: i64,
+        stop: i64,
+        step: usize,
+    ) -> Result<Value, Error> {
+        let arr = self
+            .get_first(path)?
+            .as_array()
+            .ok_or_else(|| Error::from("ERR wrong type of path value"))?;
+
+        let len = arr.len() as i64;
+        if arr.is_empty() || start >= len || stop < -len {
+            return Ok(Value::Array(vec![]));
+        }
+
+        // Both ends are inclusive, like ARRTRIM
+        let start = start.normalize(len);
+        let stop = stop.normalize(len);
+        if start > stop {
+            return Ok(Value::Array(vec![]));
+        }
+
+        Ok(Value::Array(
+            arr[start..=stop].iter().step_by(step).cloned().collect(),
+        ))
+    }
+
     pub fn get_type(&self, path: &str) -> Result<String, Error> {
         let s = RedisJSON::value_name(self.get_first(path)?);
         Ok(s.to_string())
@@ -290,10 +908,10 @@ impl RedisJSON {
             Value::Null => "null",
             Value::Bool(_) => "boolean",
             Value::Number(n) => {
-                if n.is_f64() {
-                    "number"
-                } else {
+                if RedisJSON::is_integer(n) {
                     "integer"
+                } else {
+                    "number"
                 }
             }
             Value::String(_) => "string",
@@ -302,6 +920,129 @@ impl RedisJSON {
         }
     }
 
+    ///
+    /// Total order used when sorting mixed values:
+    /// null < false < true < numbers < strings < arrays < objects
+    ///
+    /// Numbers compare numerically, strings by their bytes and arrays element by element.
+    /// Objects compare as equal, so they keep their relative order in a stable sort.
+    ///
+    pub fn cmp_values(a: &Value, b: &Value) -> Ordering {
+        match (a, b) {
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Number(a), Value::Number(b)) => RedisJSON::cmp_numbers(a, b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| RedisJSON::cmp_values(a, b))
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            _ => RedisJSON::type_rank(a).cmp(&RedisJSON::type_rank(b)),
+        }
+    }
+
+    ///
+    /// Deep equality where numbers are compared by value, so `1` equals `1.0`,
+    /// and object members in any order
+    ///
+    pub fn values_equal(a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => RedisJSON::cmp_numbers(a, b) == Ordering::Equal,
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(a, b)| RedisJSON::values_equal(a, b))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(k, a)| b.get(k).map_or(false, |b| RedisJSON::values_equal(a, b)))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    ///
+    /// Checks if needle is a structural subset of haystack: every member of an object
+    /// needle is contained in the same haystack member, and every element of an array
+    /// needle is contained in some haystack element. Other values must be equal.
+    ///
+    pub fn value_contains(haystack: &Value, needle: &Value) -> bool {
+        match (haystack, needle) {
+            (Value::Object(haystack), Value::Object(needle)) => needle.iter().all(|(k, n)| {
+                haystack
+                    .get(k)
+                    .map_or(false, |h| RedisJSON::value_contains(h, n))
+            }),
+            (Value::Array(haystack), Value::Array(needle)) => needle
+                .iter()
+                .all(|n| haystack.iter().any(|h| RedisJSON::value_contains(h, n))),
+            (haystack, needle) => RedisJSON::values_equal(haystack, needle),
+        }
+    }
+
+    pub fn cmp_numbers(a: &Number, b: &Number) -> Ordering {
+        match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => match (a.as_u64(), b.as_u64()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                // Rounding to a double keeps the order, so only numbers with the same
+                // double but a different text need an exact comparison
+                _ if a == b => Ordering::Equal,
+                _ => match a.as_f64().partial_cmp(&b.as_f64()) {
+                    Some(Ordering::Equal) | None => {
+                        match (RedisJSON::to_decimal(a), RedisJSON::to_decimal(b)) {
+                            (Some(a), Some(b)) => a.cmp(&b),
+                            _ => Ordering::Equal,
+                        }
+                    }
+                    Some(o) => o,
+                },
+            },
+        }
+    }
+
+    ///
+    /// The exact value of a number, or None when it has too many digits to compute with
+    ///
+    pub fn to_decimal(n: &Number) -> Option<BigDecimal> {
+        let text = n.to_string();
+        let (mantissa, exponent) = match text.find(&['e', 'E'][..]) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+            None => (&text[..], 0),
+        };
+        let fraction_digits = mantissa.find('.').map_or(0, |i| mantissa.len() - i - 1);
+        let scale = (fraction_digits as i64).checked_sub(exponent)?;
+        if mantissa.len() as i64 > MAX_DECIMAL_DIGITS || scale.abs() > MAX_DECIMAL_DIGITS {
+            return None;
+        }
+        BigDecimal::from_str(&text).ok()
+    }
+
+    ///
+    /// Whether the number is written without a fraction or an exponent
+    ///
+    pub fn is_integer(n: &Number) -> bool {
+        !n.to_string().contains(&['.', 'e', 'E'][..])
+    }
+
+    pub fn is_finite(n: &Number) -> bool {
+        n.as_f64().map_or(false, f64::is_finite)
+    }
+
+    fn type_rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+
     pub fn value_op<F>(&mut self, path: &str, mut fun: F) -> Result<Value, Error>
     where
         F: FnMut(&Value) -> Result<Value, Error>,
@@ -339,6 +1080,9 @@ impl RedisJSON {
                 .map_err(|e| {
                     errors.push(e.into());
                 })
+                .ok()
+                // Leave the document untouched if any of the values failed
+                .filter(|_| errors.is_empty())
                 .unwrap_or(current_data)
         };
 
@@ -349,6 +1093,35 @@ impl RedisJSON {
         }
     }
 
+    ///
+    /// Like value_op, but fun also gets the normalized path of each value, and a value
+    /// matched more than once is only updated once.
+    /// If fun fails on any of the values, none of them is updated.
+    ///
+    pub fn value_op_with_path<F>(&mut self, path: &str, mut fun: F) -> Result<Value, Error>
+    where
+        F: FnMut(&str, &Value) -> Result<Value, Error>,
+    {
+        let paths = self.match_paths(path)?;
+
+        let mut new_values = Vec::with_capacity(paths.len());
+        for segments in &paths {
+            let value = self
+                .value_at_mut(segments)
+                .ok_or("ERR path does not exist")?;
+            new_values.push(fun(&RedisJSON::normalized_path(segments), value)?);
+        }
+
+        let mut result = Value::Null;
+        for (segments, new_value) in paths.iter().zip(new_values) {
+            if let Some(value) = self.value_at_mut(segments) {
+                result = new_value.clone();
+                *value = new_value;
+            }
+        }
+        Ok(result)
+    }
+
     pub fn get_memory<'a>(&'a self, path: &'a str) -> Result<usize, Error> {
         // TODO add better calculation, handle wrappers, internals and length
         let res = match self.get_first(path)? {
@@ -370,12 +1143,112 @@ impl RedisJSON {
         }
     }
 
+    ///
+    /// Like get_first, but a missing path resolves to default when one is given
+    ///
+    pub fn get_first_or<'a>(
+        &'a self,
+        path: &'a str,
+        default: &'a Option<Value>,
+    ) -> Result<&'a Value, Error> {
+        match (self.get_values(path)?.into_iter().next(), default) {
+            (Some(value), _) => Ok(value),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err("ERR path does not exist".into()),
+        }
+    }
+
     pub fn get_values<'a>(&'a self, path: &'a str) -> Result<Vec<&'a Value>, Error> {
         let results = jsonpath_lib::select(&self.data, path)?;
         Ok(results)
     }
 }
 
+///
+/// Matches s against a glob-style pattern like Redis' SCAN MATCH does:
+/// `*`, `?`, `[abc]`, `[^abc]`, `[a-z]`, and `\` to escape a special character.
+///
+/// On a mismatch only the last `*` is retried with one more character, so the cost
+/// is at most the product of the lengths, whatever the number of `*`.
+///
+fn glob_match(pattern: &[char], s: &[char]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Where to resume after the last `*`: in the pattern, and in s
+    let mut star = None;
+    while i < s.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, i));
+            continue;
+        }
+        if let Some(len) = glob_match_char(&pattern[p..], s[i]) {
+            p += len;
+            i += 1;
+            continue;
+        }
+        match star {
+            Some((star_p, star_i)) => {
+                p = star_p;
+                i = star_i + 1;
+                star = Some((star_p, i));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+///
+/// Returns the length of the first element of pattern when it matches c
+///
+fn glob_match_char(pattern: &[char], c: char) -> Option<usize> {
+    match pattern.first()? {
+        '?' => Some(1),
+        '[' => {
+            let negate = pattern.get(1) == Some(&'^');
+            let mut i = if negate { 2 } else { 1 };
+            let mut matched = false;
+            while i < pattern.len() && pattern[i] != ']' {
+                if pattern[i] == '\\' && i + 1 < pattern.len() {
+                    matched |= pattern[i + 1] == c;
+                    i += 2;
+                } else if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+                    let (from, to) = (
+                        pattern[i].min(pattern[i + 2]),
+                        pattern[i].max(pattern[i + 2]),
+                    );
+                    matched |= from <= c && c <= to;
+                    i += 3;
+                } else {
+                    matched |= pattern[i] == c;
+                    i += 1;
+                }
+            }
+            // Past the closing bracket, if any
+            let len = (i + 1).min(pattern.len());
+            if matched != negate {
+                Some(len)
+            } else {
+                None
+            }
+        }
+        '\\' if pattern.len() > 1 => {
+            if pattern[1] == c {
+                Some(2)
+            } else {
+                None
+            }
+        }
+        p => {
+            if *p == c {
+                Some(1)
+            } else {
+                None
+            }
+        }
+    }
+}
+
 pub mod type_methods {
     use super::*;
 
@@ -385,6 +1258,7 @@ pub mod type_methods {
             0 => RedisJSON {
                 data: backward::json_rdb_load(rdb),
                 index: None, // TODO handle load from rdb
+                scan_indexes: RefCell::default(),
             },
             2 => {
                 let data = raw::load_string(rdb);
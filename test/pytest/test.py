@@ -4,9 +4,12 @@ from rmtest import BaseModuleTestCase
 import rmtest.config
 import redis
 import unittest
+import hashlib
 import json
 import sys
 import os
+import threading
+import time
 
 # Path to JSON test case files
 json_path = os.path.abspath(os.path.join(os.getcwd(), 'test/files'))
@@ -14,12 +17,9 @@ json_path = os.path.abspath(os.path.join(os.getcwd(), 'test/files'))
 # TODO: these are currently not supported so ignore them
 json_ignore = [
     'pass-json-parser-0002.json',   # UTF-8 to Unicode
-    'pass-json-parser-0005.json',   # big numbers
     'pass-json-parser-0006.json',   # UTF-8 to Unicode
     'pass-json-parser-0007.json',   # UTF-8 to Unicode
     'pass-json-parser-0012.json',   # UTF-8 to Unicode
-    'pass-jsonsl-1.json',           # big numbers
-    'pass-jsonsl-yelp.json',        # float precision
 ]
 
 # Some basic documents to use in the tests
@@ -198,6 +198,94 @@ class ReJSONTestCase(BaseReJSONTest):
             # with self.assertRaises(redis.exceptions.ResponseError) as cm:
             #     r.execute_command('JSON.SET', 'test', '.foo[1]', 'null', 'XX')
     
+    def testSetMkpath(self):
+        """Test JSON.SET's MKPATH subcommand"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"arr":[1],"s":"str"}'))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.SET', 'test', '$.a.b.c', '1')
+            self.assertIn("$['a']", str(cm.exception))
+            self.assertNotIn("$['a']['b']", str(cm.exception))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.SET', 'test', '$.s.t', '1')
+            self.assertIn("$['s']['t']", str(cm.exception))
+            self.assertEqual('{"arr":[1],"s":"str"}', r.execute_command('JSON.GET', 'test', '.'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.a.b.c', '1', 'MKPATH'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.x[0]["y z"]', 'true', 'MKPATH'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.arr[1].v', '2', 'MKPATH'))
+            self.assertIsNone(r.execute_command('JSON.SET', 'test', '$.a.b.c', '3', 'MKPATH', 'NX'))
+            self.assertIsNone(r.execute_command('JSON.SET', 'test', '$.a.b.d', '3', 'MKPATH', 'XX'))
+            self.assertEqual('{"arr":[1,{"v":2}],"s":"str","a":{"b":{"c":1}},"x":[{"y z":true}]}',
+                             r.execute_command('JSON.GET', 'test', '.'))
+
+            # the error names the segment that can't be created
+            data = r.execute_command('JSON.GET', 'test', '.')
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.SET', 'test', '$.s.t.u', '1', 'MKPATH')
+            self.assertIn("$['s']['t']", str(cm.exception))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.SET', 'test', '$.arr[5]', '1', 'MKPATH')
+            self.assertIn("$['arr'][5]", str(cm.exception))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.SET', 'test', '$.n[1]', '1', 'MKPATH')
+            self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+
+            # a new key is created as an object
+            self.assertOk(r.execute_command('JSON.SET', 'new', '.a.b', '1', 'MKPATH'))
+            self.assertEqual('{"a":{"b":1}}', r.execute_command('JSON.GET', 'new', '.'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.SET', 'other', '$[0]', '1', 'MKPATH')
+            self.assertFalse(r.exists('other'))
+
+    def testSetArrayIndicesAndSlices(self):
+        """Test JSON.SET with negative indices, slices and unions"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"items":[{"id":1},{"id":2}],"arr":[1,2,3,4,5]}'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.items[-1].done', 'true'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.items[-2]["done"]', 'false', 'NX'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.items[-1].id', '3', 'XX'))
+            # like in reads, an index before the start selects the first element
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.items[-5].tag', '"first"'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.SET', 'test', '$.items[2].done', 'true')
+            self.assertEqual('[{"id":1,"done":false,"tag":"first"},{"id":3,"done":true}]', r.execute_command('JSON.GET', 'test', '.items'))
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '$.arr[-1]', '50'))
+            self.assertEqual(2, r.execute_command('JSON.SET', 'test', '$.arr[1:3]', '0'))
+            self.assertEqual(2, r.execute_command('JSON.SET', 'test', '$.arr[0,-2]', '"x"', 'XX'))
+            self.assertEqual('["x",0,0,"x",50]', r.execute_command('JSON.GET', 'test', '.arr'))
+
+    def testSetFilterPaths(self):
+        """Test JSON.SET with paths that select many values"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            data = '{"orders":[{"id":1,"status":"open"},{"id":2,"status":"done"},{"id":3,"status":"open"}]}'
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', data))
+            self.assertEqual(2, r.execute_command('JSON.SET', 'test', '$.orders[?(@.status=="open")].status', '"closed"'))
+            self.assertEqual(0, r.execute_command('JSON.SET', 'test', '$.orders[?(@.status=="open")].status', '"closed"'))
+            self.assertEqual(0, r.execute_command('JSON.SET', 'test', '$.orders[?(@.id>1)].missing', 'true'))
+            self.assertEqual(0, r.execute_command('JSON.SET', 'test', '$.orders[*].id', '0', 'NX'))
+            self.assertEqual(3, r.execute_command('JSON.SET', 'test', '$..id', '0', 'XX'))
+            self.assertEqual('{"orders":[{"id":0,"status":"closed"},{"id":0,"status":"done"},{"id":0,"status":"closed"}]}',
+                             r.execute_command('JSON.GET', 'test', '.'))
+
+            # an invalid path leaves the document untouched
+            data = r.execute_command('JSON.GET', 'test', '.')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.SET', 'test', '$.orders[?(@.id>', '1')
+            self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+
+            # nothing is created for a missing key either
+            self.assertEqual(0, r.execute_command('JSON.SET', 'missing', '$.a[*]', '1'))
+            self.assertFalse(r.exists('missing'))
+
     def testGetNonExistantPathsFromBasicDocumentShouldFail(self):
         """Test failure of getting non-existing values"""
     
@@ -291,7 +379,69 @@ class ReJSONTestCase(BaseReJSONTest):
             # Test that MGET fails on path errors
             with self.assertRaises(redis.exceptions.ResponseError) as cm:
                 r.execute_command('JSON.MGET', 'doc:0', 'doc:1', '42isnotapath')
+
+    def testGetDefault(self):
+        """Test the DEFAULT option of JSON.GET and JSON.MGET"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'doc1', '.', '{"a":1,"b":null}'))
+            self.assertOk(r.execute_command('JSON.SET', 'doc2', '.', '{"c":2}'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.GET', 'doc1', '.c')
+            self.assertEqual('0', r.execute_command('JSON.GET', 'doc1', '.c', 'DEFAULT', '0'))
+            self.assertEqual('1', r.execute_command('JSON.GET', 'doc1', '.a', 'DEFAULT', '0'))
+            self.assertEqual('null', r.execute_command('JSON.GET', 'doc1', '.b', 'DEFAULT', '0'))
+            self.assertEqual('{"x":[]}', r.execute_command('JSON.GET', 'doc1', 'DEFAULT', '{"x":[]}', '.c'))
+            self.assertIsNone(r.execute_command('JSON.GET', 'missing', '.c', 'DEFAULT', '0'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.GET', 'doc1', '.c', 'DEFAULT', '{')
+
+            # per path in a multi path get, or for all the paths when given first
+            self.assertEqual('{".a":1,".c":"none",".d":null}', r.execute_command('JSON.GET', 'doc1', '.a', '.c', 'DEFAULT', '"none"', '.d'))
+            self.assertEqual('{".c":0,".d":1}', r.execute_command('JSON.GET', 'doc1', 'DEFAULT', '0', '.c', '.d', 'DEFAULT', '1'))
+
+            self.assertEqual(['1', '0', None], r.execute_command('JSON.MGET', 'doc1', 'doc2', 'missing', '.a', 'DEFAULT', '0'))
+            self.assertEqual(['null', '2'], r.execute_command('JSON.MGET', 'doc1', 'doc2', '.c', 'DEFAULT', 'null'))
     
+    def testGetProjectAndExclude(self):
+        """Test the PROJECT and EXCLUDE options of JSON.GET"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            doc = '{"a":1,"user":{"name":"n","pw":"x"},"items":[{"id":1,"v":2},{"v":3},{"id":4}]}'
+            self.assertOk(r.execute_command('JSON.SET', 'doc', '.', doc))
+            self.assertEqual('{"user":{"name":"n"},"items":[{"id":1},{"id":4}]}', r.execute_command('JSON.GET', 'doc', 'PROJECT', '$.user.name', '$.items[*].id'))
+            self.assertEqual('{"user":{"name":"n"},"x":1}', r.execute_command('JSON.GET', 'doc', 'PROJECT', '.user.name', '.a', 'AS', 'x'))
+            self.assertEqual('{}', r.execute_command('JSON.GET', 'doc', 'PROJECT', '.missing'))
+
+            self.assertEqual('{"a":1,"user":{"name":"n"},"items":[{"id":1},{},{"id":4}]}', r.execute_command('JSON.GET', 'doc', 'EXCLUDE', '.user.pw', '$.items[*].v'))
+            self.assertEqual('{"user":{"name":"n"}}', r.execute_command('JSON.GET', 'doc', 'PROJECT', '.user', 'EXCLUDE', '.user.pw'))
+            self.assertEqual('{"items":[{"id":1},{},{"id":4}]}', r.execute_command('JSON.GET', 'doc', 'EXCLUDE', '$.items[*].v', '.a', '.user', 'CANONICAL'))
+
+            # the stored document is unchanged
+            self.assertEqual(doc, r.execute_command('JSON.GET', 'doc'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.GET', 'doc', '.a', 'PROJECT', '.user')
+            self.assertIsNone(r.execute_command('JSON.GET', 'missing', 'PROJECT', '.a'))
+
+    def testGetMaxDepth(self):
+        """Test the MAXDEPTH option of JSON.GET"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'doc', '.', '{"a":{"b":{"c":1},"l":[1,[2]]},"e":[]}'))
+            self.assertEqual('{"a":{"$truncated":"object","size":2},"e":{"$truncated":"array","size":0}}', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '0'))
+            self.assertEqual('{"a":{"b":{"$truncated":"object","size":1},"l":{"$truncated":"array","size":2}},"e":[]}', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '1'))
+            self.assertEqual('{"b":{"c":1},"l":[1,{"$truncated":"array","size":1}]}', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '1', '.a'))
+            self.assertEqual('{".a":{"b":{"$truncated":"object","size":1},"l":{"$truncated":"array","size":2}},".e":[]}', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '0', '.a', '.e'))
+            self.assertEqual('{"a":{"b":{"$truncated":"object","size":1}}}', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '1', 'PROJECT', '.a.b'))
+            self.assertEqual('1', r.execute_command('JSON.GET', 'doc', 'MAXDEPTH', '0', '.a.b.c'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.GET', 'doc', 'MAXDEPTH', '-1')
+
     def testDelCommand(self):
         """Test REJSON.DEL command"""
     
@@ -339,6 +489,27 @@ class ReJSONTestCase(BaseReJSONTest):
             self.assertEqual(r.execute_command('JSON.DEL', 'test', '.'), 1)
             self.assertIsNone(r.execute_command('JSON.GET', 'test'))
     
+    def testDelManyCommand(self):
+        """Test JSON.DEL with paths that match many values"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"arr":[0,1,2,3,4,5],"n":[null,1,null],"o":[{"x":1},{"x":2},{"x":1}],"a":{"a":{"a":1}}}'))
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$.arr[0,2,4]'), 3)
+            self.assertEqual(r.execute_command('JSON.GET', 'test', '.arr'), '[1,3,5]')
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$.arr[1:]'), 2)
+            self.assertEqual(r.execute_command('JSON.GET', 'test', '.arr'), '[1]')
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$.n[0,2]'), 2)
+            self.assertEqual(r.execute_command('JSON.GET', 'test', '.n'), '[1]')
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$.o[?(@.x==1)]'), 2)
+            self.assertEqual(r.execute_command('JSON.GET', 'test', '.o'), '[{"x":2}]')
+            # nested matches are deleted with the outer one
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$..a'), 1)
+            self.assertEqual(r.execute_command('JSON.DEL', 'test', '$.o[*].missing'), 0)
+            self.assertEqual(r.execute_command('JSON.GET', 'test', '.'), '{"arr":[1],"n":[1],"o":[{"x":2}]}')
+
     def testObjectCRUD(self):
         with self.redis() as r:
             r.client_setname(self._testMethodName)
@@ -428,6 +599,10 @@ class ReJSONTestCase(BaseReJSONTest):
             self.assertEqual(7, r.execute_command('JSON.ARRAPPEND', 'test', '.', 2, 3))
             data = json.loads(r.execute_command('JSON.GET', 'test', '.'))
             self.assertListEqual([-3, -2, -1, 0, 1, 2, 3], data)
+            self.assertEqual(8, r.execute_command('JSON.ARRINSERT', 'test', '.', 7, 4))
+            self.assertEqual('4', r.execute_command('JSON.ARRPOP', 'test', '.'))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRINSERT', 'test', '.', 8, 4)
 
             # Test replacing elements in the array
             self.assertOk(r.execute_command('JSON.SET', 'test', '[0]', '"-inf"'))
@@ -486,6 +661,38 @@ class ReJSONTestCase(BaseReJSONTest):
             self.assertEqual(r.execute_command('JSON.ARRINDEX', 'test', '.arr', 2, 3), 4)
             # self.assertEqual(r.execute_command('JSON.ARRINDEX', 'test', '.arr', '[4]'), -1)
 
+    def testArrAddUniqueAndArrRemCommands(self):
+        """Test JSON.ARRADDUNIQUE and JSON.ARRREM commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"tags": ["a", "b"], "n": 1}'))
+            self.assertEqual(3, r.execute_command('JSON.ARRADDUNIQUE', 'test', '.tags', '"b"', '"c"', '"c"'))
+            self.assertListEqual(['a', 'b', 'c'], json.loads(r.execute_command('JSON.GET', 'test', '.tags')))
+            self.assertEqual(4, r.execute_command('JSON.ARRADDUNIQUE', 'test', '.tags', '{"x":[1]}'))
+            self.assertEqual(4, r.execute_command('JSON.ARRADDUNIQUE', 'test', '.tags', '{"x":[1]}'))
+
+            self.assertEqual(3, r.execute_command('JSON.ARRREM', 'test', '.tags', '{"x":[1]}'))
+            self.assertEqual(3, r.execute_command('JSON.ARRREM', 'test', '.tags', '"z"'))
+            self.assertListEqual(['a', 'b', 'c'], json.loads(r.execute_command('JSON.GET', 'test', '.tags')))
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.tags', '[1, 2, 1, 3, 1]'))
+            self.assertEqual(4, r.execute_command('JSON.ARRREM', 'test', '.tags', 1, 'COUNT', 1))
+            self.assertListEqual([2, 1, 3, 1], json.loads(r.execute_command('JSON.GET', 'test', '.tags')))
+            self.assertEqual(3, r.execute_command('JSON.ARRREM', 'test', '.tags', 1, 'COUNT', -1))
+            self.assertListEqual([2, 1, 3], json.loads(r.execute_command('JSON.GET', 'test', '.tags')))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.tags', '[1, 2, 1, 3, 1]'))
+            self.assertEqual(2, r.execute_command('JSON.ARRREM', 'test', '.tags', 1))
+            self.assertListEqual([2, 3], json.loads(r.execute_command('JSON.GET', 'test', '.tags')))
+
+            # test a wrong type
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRADDUNIQUE', 'test', '.n', 1)
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRREM', 'test', '.n', 1)
+
     def testArrTrimCommand(self):
         """Test JSON.ARRTRIM command"""
 
@@ -507,6 +714,33 @@ class ReJSONTestCase(BaseReJSONTest):
             self.assertEqual(r.execute_command('JSON.ARRTRIM', 'test', '.arr', 99, 2), 0)
             self.assertListEqual(json.loads(r.execute_command('JSON.GET', 'test', '.arr')), [])
 
+    def testArrSliceCommand(self):
+        """Test JSON.ARRSLICE command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test',
+                                            '.', '{ "arr": [0, 1, 2, 3, 4, 5, 6] }'))
+            self.assertListEqual([1, 2, 3], json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', 1, 3)))
+            self.assertListEqual([5, 6], json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', -2, -1)))
+            self.assertListEqual([0, 1, 2, 3, 4, 5, 6],
+                                 json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', -99, 99)))
+            self.assertListEqual([0, 2, 4, 6],
+                                 json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', 0, -1, 'STEP', 2)))
+            self.assertListEqual([], json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', 4, 2)))
+            self.assertListEqual([], json.loads(r.execute_command('JSON.ARRSLICE', 'test', '.arr', 7, 9)))
+
+            # the array is not modified
+            self.assertEqual(7, r.execute_command('JSON.ARRLEN', 'test', '.arr'))
+
+            self.assertIsNone(r.execute_command('JSON.ARRSLICE', 'missing', '.arr', 0, 1))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSLICE', 'test', '.arr', 0, 1, 'STEP', 0)
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSLICE', 'test', '.', 0, 1)
+
     def testArrPopCommand(self):
         """Test JSON.ARRPOP command"""
     
@@ -527,6 +761,259 @@ class ReJSONTestCase(BaseReJSONTest):
             # self.assertEqual('3', r.execute_command('JSON.ARRPOP', 'test'))
             # self.assertIsNone(r.execute_command('JSON.ARRPOP', 'test'))
     
+            # test popping several elements
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '[1,2,3,4,5]'))
+            self.assertListEqual(['5', '4'], r.execute_command('JSON.ARRPOP', 'test', '.', 'COUNT', 2))
+            self.assertListEqual(['1', '2'], r.execute_command('JSON.ARRPOP', 'test', '.', 0, 'COUNT', 2))
+            self.assertListEqual(['3'], r.execute_command('JSON.ARRPOP', 'test', '.', 'COUNT', 9))
+            self.assertListEqual([], r.execute_command('JSON.ARRPOP', 'test', '.', 'COUNT', 1))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRPOP', 'test', '.', 'COUNT', 0)
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '[1,2,3,4,5]'))
+            self.assertListEqual(['4', '3'], r.execute_command('JSON.ARRPOP', 'test', '.', -2, 'COUNT', 2))
+            self.assertListEqual(['2', '5'], r.execute_command('JSON.ARRPOP', 'test', '.', 1, 'COUNT', 3))
+
+            # COUNT comes after the path and the index, so this pops from the `count` path
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"count":[1,2,3,4]}'))
+            self.assertEqual('4', r.execute_command('JSON.ARRPOP', 'test', 'count', 3))
+            self.assertEqual('3', r.execute_command('JSON.ARRPOP', 'test', 'count'))
+
+    def testArrSpliceCommand(self):
+        """Test JSON.ARRSPLICE command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"arr": [1,2,3,4,5]}'))
+            self.assertListEqual(['2', '3'], r.execute_command('JSON.ARRSPLICE', 'test', '.arr', 1, 2, '"a"'))
+            self.assertListEqual([1, 'a', 4, 5], json.loads(r.execute_command('JSON.GET', 'test', '.arr')))
+            self.assertListEqual(['4', '5'], r.execute_command('JSON.ARRSPLICE', 'test', '.arr', -2, 99))
+            self.assertListEqual([], r.execute_command('JSON.ARRSPLICE', 'test', '.arr', 99, 1, '[6]', '{"b":7}'))
+            self.assertListEqual([1, 'a', [6], {'b': 7}], json.loads(r.execute_command('JSON.GET', 'test', '.arr')))
+            self.assertListEqual([], r.execute_command('JSON.ARRSPLICE', 'test', '.arr', 0, 0, 0))
+            self.assertListEqual([0, 1, 'a', [6], {'b': 7}], json.loads(r.execute_command('JSON.GET', 'test', '.arr')))
+
+            # test a wrong type
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSPLICE', 'test', '.', 0, 1)
+
+    def testBArrPopCommand(self):
+        """Test JSON.BARRPOP command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"q": [1, 2]}'))
+
+            # a non-empty array is popped right away
+            self.assertEqual('2', r.execute_command('JSON.BARRPOP', 'test', '.q', 0))
+            self.assertEqual('1', r.execute_command('JSON.BARRPOP', 'test', '.q', 0, 0))
+
+            # an empty array times out with nil
+            self.assertIsNone(r.execute_command('JSON.BARRPOP', 'test', '.q', 0.1))
+
+            # a blocked client is woken up by ARRAPPEND and ARRINSERT
+            def push(cmd, *args):
+                time.sleep(0.2)
+                r.execute_command(cmd, 'test', '.q', *args)
+
+            for cmd, args in (('JSON.ARRAPPEND', ('"a"',)), ('JSON.ARRINSERT', (0, '"b"'))):
+                t = threading.Thread(target=push, args=(cmd,) + args)
+                t.start()
+                self.assertEqual(json.dumps(json.loads(args[-1])),
+                                 r.execute_command('JSON.BARRPOP', 'test', '.q', 5))
+                t.join()
+            self.assertEqual(0, r.execute_command('JSON.ARRLEN', 'test', '.q'))
+
+            # test a wrong type and a negative timeout
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.q', '"str"'))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.BARRPOP', 'test', '.q', 0)
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.BARRPOP', 'test', '.q', -1)
+
+            # timeouts that aren't finite don't block forever
+            for timeout in ('nan', 'inf', '-inf', '1e300'):
+                with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                    r.execute_command('JSON.BARRPOP', 'test', '.q', timeout)
+
+            # an invalid path is an error instead of blocking
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.BARRPOP', 'test', '$.q[', 0)
+
+            # inside MULTI an empty array replies nil without blocking
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.q', '[]'))
+            p = r.pipeline(transaction=True)
+            p.execute_command('JSON.BARRPOP', 'test', '.q', 0)
+            self.assertEqual([None], p.execute())
+
+    def testArrSortCommand(self):
+        """Test JSON.ARRSORT command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '[3, 1, 10, 2]'))
+            self.assertEqual(4, r.execute_command('JSON.ARRSORT', 'test', '.'))
+            self.assertListEqual([1, 2, 3, 10], json.loads(r.execute_command('JSON.GET', 'test', '.')))
+            self.assertEqual(4, r.execute_command('JSON.ARRSORT', 'test', '.', 'DESC'))
+            self.assertListEqual([10, 3, 2, 1], json.loads(r.execute_command('JSON.GET', 'test', '.')))
+            self.assertEqual(4, r.execute_command('JSON.ARRSORT', 'test', '.', 'ALPHA'))
+            self.assertListEqual([1, 10, 2, 3], json.loads(r.execute_command('JSON.GET', 'test', '.')))
+
+            # mixed types: null < false < true < numbers < strings < arrays < objects
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.',
+                                            '[{"a":1}, "b", [1], 2, true, null, false, "a"]'))
+            self.assertEqual(8, r.execute_command('JSON.ARRSORT', 'test', '.'))
+            self.assertListEqual([None, False, True, 2, 'a', 'b', [1], {'a': 1}],
+                                 json.loads(r.execute_command('JSON.GET', 'test', '.')))
+
+            # sort objects by a relative path, missing keys sort as null
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.',
+                                            '{"board": [{"n":"a","s":5}, {"n":"b","s":7}, {"n":"c"}, {"n":"d","s":5}]}'))
+            self.assertEqual(4, r.execute_command('JSON.ARRSORT', 'test', '.board', 'BY', '.s', 'DESC'))
+            data = json.loads(r.execute_command('JSON.GET', 'test', '.board'))
+            self.assertListEqual(['b', 'a', 'd', 'c'], [x['n'] for x in data])
+
+            # NUMERIC requires numbers and leaves the array untouched otherwise
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSORT', 'test', '.board', 'BY', '.s', 'NUMERIC')
+            self.assertListEqual(['b', 'a', 'd', 'c'],
+                                 [x['n'] for x in json.loads(r.execute_command('JSON.GET', 'test', '.board'))])
+
+            # test a wrong type and a syntax error
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSORT', 'test', '.')
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.ARRSORT', 'test', '.board', 'SIDEWAYS')
+
+    def testCanonicalAndHashCommands(self):
+        """Test JSON.GET CANONICAL and JSON.HASH commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            data = r'''{"numbers":[333333333.33333329,1E30,4.50,2e-3,0.000000000000000000000000001],"string":"\u20ac$\u000F\u000aA'B\"\\\\\"/","literals":[null,true,false]}'''
+            canonical = u'{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"\u20ac$' + r'''\u000f\nA'B\"\\\\\"/"}'''
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', data))
+            self.assertEqual(canonical, r.execute_command('JSON.GET', 'test', 'CANONICAL'))
+            self.assertEqual('[333333333.3333333,1e+30,4.5,0.002,1e-27]', r.execute_command('JSON.GET', 'test', 'CANONICAL', '.numbers'))
+            self.assertEqual('{".literals":[null,true,false],".numbers[1]":1e+30}', r.execute_command('JSON.GET', 'test', 'CANONICAL', '.numbers[1]', '.literals'))
+
+            self.assertEqual(hashlib.sha256(canonical.encode('utf-8')).hexdigest(), r.execute_command('JSON.HASH', 'test'))
+            self.assertEqual(hashlib.sha256(canonical.encode('utf-8')).hexdigest(), r.execute_command('JSON.HASH', 'test', '.', 'sha256'))
+            self.assertEqual(hashlib.sha256(b'[null,true,false]').hexdigest(), r.execute_command('JSON.HASH', 'test', '.literals'))
+            self.assertEqual(16, len(r.execute_command('JSON.HASH', 'test', '.literals', 'XXH3')))
+
+            # the hash doesn't depend on the members order or the number format
+            self.assertOk(r.execute_command('JSON.SET', 'other', '.', '{"b":[1.0,2e1],"a":"x"}'))
+            self.assertOk(r.execute_command('JSON.SET', 'another', '.', '{"a":"x","b":[1,20]}'))
+            self.assertEqual(r.execute_command('JSON.HASH', 'other', 'XXH3'), r.execute_command('JSON.HASH', 'another', 'XXH3'))
+
+            self.assertIsNone(r.execute_command('JSON.HASH', 'missing'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.HASH', 'test', '.', 'MD5')
+
+    def testExistsAndPathsCommands(self):
+        """Test JSON.EXISTS and JSON.PATHS commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"items":[{"id":1},{"id":2,"done":true},{"name":"x"},{"id":4}],"it\'s":{"id":5}}'))
+            self.assertEqual(1, r.execute_command('JSON.EXISTS', 'test', '.items'))
+            self.assertEqual(3, r.execute_command('JSON.EXISTS', 'test', '$.items[*].id'))
+            self.assertEqual(0, r.execute_command('JSON.EXISTS', 'test', '.missing'))
+            self.assertEqual(0, r.execute_command('JSON.EXISTS', 'missing', '.items'))
+
+            self.assertEqual(["$['items'][0]['id']", "$['items'][1]['id']", "$['items'][3]['id']"],
+                             r.execute_command('JSON.PATHS', 'test', '$.items[*].id'))
+            self.assertEqual(["$['items'][1]"], r.execute_command('JSON.PATHS', 'test', '$.items[?(@.done==true)]'))
+            self.assertEqual(["$['items'][0]['id']", "$['items'][1]['id']", "$['items'][3]['id']", "$['it\\'s']['id']"],
+                             r.execute_command('JSON.PATHS', 'test', '$..id'))
+            self.assertEqual(['$'], r.execute_command('JSON.PATHS', 'test', '.'))
+            self.assertEqual([], r.execute_command('JSON.PATHS', 'test', '.missing'))
+            self.assertIsNone(r.execute_command('JSON.PATHS', 'missing', '.'))
+
+            # a value matched more than once is counted once, like in JSON.PATHS
+            for path in ("$.items[0,0]", "$['items','items']", '$..items'):
+                self.assertEqual(len(r.execute_command('JSON.PATHS', 'test', path)),
+                                 r.execute_command('JSON.EXISTS', 'test', path))
+            self.assertEqual(1, r.execute_command('JSON.EXISTS', 'test', '$.items[0,0]'))
+
+    def testEqualsAndContainsCommands(self):
+        """Test JSON.EQUALS and JSON.CONTAINS commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'doc1', '.', '{"a":1,"b":{"c":[1,2,{"d":"x"}]},"e":null}'))
+            self.assertOk(r.execute_command('JSON.SET', 'doc2', '.', '{"e":null,"b":{"c":[1.0,2,{"d":"x"}]},"a":1e0}'))
+            self.assertOk(r.execute_command('JSON.SET', 'doc3', '.', '{"list":[[1,2,{"d":"x"}]]}'))
+            self.assertEqual(1, r.execute_command('JSON.EQUALS', 'doc1', '.', 'doc2', '.'))
+            self.assertEqual(1, r.execute_command('JSON.EQUALS', 'doc1', '.b.c', 'doc3', '.list[0]'))
+            self.assertEqual(0, r.execute_command('JSON.EQUALS', 'doc1', '.', 'doc3', '.'))
+            self.assertEqual(0, r.execute_command('JSON.EQUALS', 'doc1', '.b.c', 'doc1', '.b'))
+            self.assertIsNone(r.execute_command('JSON.EQUALS', 'doc1', '.', 'missing', '.'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.EQUALS', 'doc1', '.', 'doc2', '.missing')
+
+            self.assertEqual(1, r.execute_command('JSON.CONTAINS', 'doc1', '.', '{"b":{"c":[{"d":"x"},2]}}'))
+            self.assertEqual(1, r.execute_command('JSON.CONTAINS', 'doc1', '.', '{}'))
+            self.assertEqual(1, r.execute_command('JSON.CONTAINS', 'doc1', '.a', '1.0'))
+            self.assertEqual(1, r.execute_command('JSON.CONTAINS', 'doc1', '.e', 'null'))
+            self.assertEqual(0, r.execute_command('JSON.CONTAINS', 'doc1', '.', '{"b":{"c":[3]}}'))
+            self.assertEqual(0, r.execute_command('JSON.CONTAINS', 'doc1', '.', '{"f":1}'))
+            self.assertEqual(0, r.execute_command('JSON.CONTAINS', 'doc1', '.b', '[1]'))
+            self.assertIsNone(r.execute_command('JSON.CONTAINS', 'missing', '.', '{}'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.CONTAINS', 'doc1', '.', '{')
+
+    def testKeyOrder(self):
+        """Test that object members keep their insertion order"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"z":1,"b":{"y":2,"a":3},"m":4,"c":5}'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.d', '6'))
+            self.assertEqual(1, r.execute_command('JSON.DEL', 'test', '.m'))
+            self.assertEqual(1, r.execute_command('JSON.RENAME', 'test', '.z', 'x'))
+            data = '{"x":1,"b":{"y":2,"a":3},"c":5,"d":6}'
+            for _ in r.retry_with_rdb_reload():
+                self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+                self.assertEqual(['x', 'b', 'c', 'd'], r.execute_command('JSON.OBJKEYS', 'test', '.'))
+                self.assertEqual(['y', 'a'], r.execute_command('JSON.OBJKEYS', 'test', '.b'))
+
+    def testRenameCommand(self):
+        """Test JSON.RENAME command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"users":[{"userId":1,"name":"a"},{"name":"b","userId":2,"user_id":0}],"userId":3}'))
+            self.assertEqual(1, r.execute_command('JSON.RENAME', 'test', '$.users[*].userId', 'user_id', 'NX'))
+            self.assertEqual('[{"user_id":1,"name":"a"},{"name":"b","userId":2,"user_id":0}]', r.execute_command('JSON.GET', 'test', '.users'))
+            self.assertEqual(2, r.execute_command('JSON.RENAME', 'test', '$..userId', 'user_id'))
+            self.assertEqual('{"users":[{"user_id":1,"name":"a"},{"name":"b","user_id":2}],"user_id":3}', r.execute_command('JSON.GET', 'test', '.'))
+            self.assertEqual(0, r.execute_command('JSON.RENAME', 'test', '.missing', 'x'))
+            self.assertEqual(0, r.execute_command('JSON.RENAME', 'test', '.users[0]', 'x'))
+
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.RENAME', 'test', '.user_id', 'x', 'FOO')
+
+            # many members of the same object can't all take the new name
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"a":1,"b":2}'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.RENAME', 'test', '$.*', 'c')
+            self.assertEqual('{"a":1,"b":2}', r.execute_command('JSON.GET', 'test', '.'))
+            self.assertEqual(1, r.execute_command('JSON.RENAME', 'test', '$.*', 'c', 'NX'))
+            self.assertEqual('{"a":1,"c":2}', r.execute_command('JSON.GET', 'test', '.'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.RENAME', 'missing', '.user_id', 'x')
+
     def testTypeCommand(self):
         """Test JSON.TYPE command"""
     
@@ -595,6 +1082,58 @@ class ReJSONTestCase(BaseReJSONTest):
             with self.assertRaises(redis.exceptions.ResponseError) as cm:
                 r.execute_command('JSON.OBJKEYS', 'test', '.null')
     
+    def testObjScanCommand(self):
+        """Test JSON.OBJSCAN command"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            members = {'k{}'.format(i): i for i in range(100)}
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', json.dumps(members)))
+
+            # members present during the whole scan are returned while others come and go
+            seen = set()
+            cursor = '0'
+            while True:
+                cursor, names = r.execute_command('JSON.OBJSCAN', 'test', '.', cursor, 'COUNT', 7)
+                seen.update(names)
+                r.execute_command('JSON.SET', 'test', '.new{}'.format(len(seen)), '1')
+                r.execute_command('JSON.DEL', 'test', '.k{}'.format(99 - len(seen) % 10))
+                if cursor == '0':
+                    break
+            for i in range(90):
+                self.assertTrue('k{}'.format(i) in seen, i)
+
+            cursor, res = r.execute_command('JSON.OBJSCAN', 'test', '.', 0, 'MATCH', 'k1?', 'COUNT', 1000, 'WITHVALUES')
+            self.assertEqual('0', cursor)
+            self.assertEqual({'k{}'.format(i): str(i) for i in range(10, 20)}, dict(zip(res[::2], res[1::2])))
+
+            # many wildcards don't make the match backtrack exponentially
+            cursor, res = r.execute_command('JSON.OBJSCAN', 'test', '.', 0, 'MATCH', '*' * 50 + 'k*1*1', 'COUNT', 1000)
+            self.assertEqual('0', cursor)
+            self.assertEqual(['k11'], res)
+
+            # each call only reads count members of a large object
+            self.assertOk(r.execute_command('JSON.SET', 'large', '.', json.dumps({'m{}'.format(i): i for i in range(100000)})))
+            start = time.time()
+            cursor, calls, scanned = '0', 0, 0
+            while True:
+                cursor, names = r.execute_command('JSON.OBJSCAN', 'large', '.', cursor, 'COUNT', 10)
+                calls += 1
+                scanned += len(names)
+                if cursor == '0':
+                    break
+            self.assertEqual(100000, scanned)
+            self.assertGreaterEqual(calls, 10000)
+            # hashing the whole object on each of the calls takes tens of seconds
+            self.assertLess(time.time() - start, 10)
+
+            self.assertEqual(['0', []], r.execute_command('JSON.OBJSCAN', 'missing', '.', 0))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.OBJSCAN', 'test', '.k1', 0)
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.OBJSCAN', 'test', '.', 'x')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.OBJSCAN', 'test', '.', 0, 'COUNT', 0)
+
     def testNumIncrCommand(self):
         """Test JSON.NUMINCRBY command"""
     
@@ -630,7 +1169,193 @@ class ReJSONTestCase(BaseReJSONTest):
             res = json.loads(r.execute_command('JSON.GET', 'foo', '.'))
             self.assertEqual(1, res['foo'])
             self.assertEqual(84, res['bar'])
-    
+
+    def testNumMaxMinAndClamp(self):
+        """Test JSON.NUMMAXBY, JSON.NUMMINBY and the CLAMP option"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"hwm":5,"low":1.5,"rate":8}'))
+            self.assertEqual('7', r.execute_command('JSON.NUMMAXBY', 'test', '.hwm', 7))
+            self.assertEqual('7', r.execute_command('JSON.NUMMAXBY', 'test', '.hwm', 3))
+            self.assertEqual('1.5', r.execute_command('JSON.NUMMINBY', 'test', '.low', '2.5'))
+            self.assertEqual('0.25', r.execute_command('JSON.NUMMINBY', 'test', '.low', '0.25'))
+            self.assertEqual('12345678901234567890', r.execute_command('JSON.NUMMAXBY', 'test', '.hwm', '12345678901234567890'))
+
+            # the kept number is unchanged, even against a number of another kind
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.hwm', '7'))
+            self.assertEqual('7', r.execute_command('JSON.NUMMAXBY', 'test', '.hwm', '3.5'))
+            self.assertEqual('integer', r.execute_command('JSON.TYPE', 'test', '.hwm'))
+            self.assertEqual('7.50', r.execute_command('JSON.NUMMAXBY', 'test', '.hwm', '7.50'))
+            self.assertEqual('7.50', r.execute_command('JSON.NUMMINBY', 'test', '.hwm', '7.5'))
+            self.assertEqual('number', r.execute_command('JSON.TYPE', 'test', '.hwm'))
+
+            self.assertEqual('10', r.execute_command('JSON.NUMINCRBY', 'test', '.rate', 5, 'CLAMP', 0, 10))
+            self.assertEqual('0', r.execute_command('JSON.NUMINCRBY', 'test', '.rate', -20, 'CLAMP', 0, 10))
+            self.assertEqual('4', r.execute_command('JSON.NUMINCRBY', 'test', '.rate', 4, 'clamp', 0, 10))
+            self.assertEqual('4', r.execute_command('JSON.GET', 'test', '.rate'))
+
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.rate', 1, 'CLAMP', 10, 0)
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.rate', 1, 'CLAMP', 0, '"a"')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.rate', 1, 'CLAMP', 0)
+
+            # options that don't apply to a command are a syntax error
+            for cmd in ('JSON.NUMMAXBY', 'JSON.NUMMINBY'):
+                for option in ('SATURATE', 'CREATE'):
+                    self.assertRaises(redis.exceptions.ResponseError, r.execute_command, cmd, 'test', '.rate', 1, option)
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMMULTBY', 'test', '.rate', 1, 'CREATE')
+            self.assertEqual('4', r.execute_command('JSON.GET', 'test', '.rate'))
+
+    def testNumIncrCreate(self):
+        """Test JSON.NUMINCRBY with CREATE"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"stats":{"total":1},"list":[{}],"s":"str"}'))
+            self.assertEqual('1', r.execute_command('JSON.NUMINCRBY', 'test', '$.stats.daily["2026-10-16"]', 1, 'CREATE'))
+            self.assertEqual('3', r.execute_command('JSON.NUMINCRBY', 'test', '$.stats.daily["2026-10-16"]', 2, 'CREATE'))
+            self.assertEqual('2', r.execute_command('JSON.NUMINCRBY', 'test', '.stats.total', 1, 'CREATE'))
+            self.assertEqual('0.5', r.execute_command('JSON.NUMINCRBY', 'test', '.list[0].a.b', 0.5, 'CREATE'))
+            self.assertEqual('{"stats":{"total":2,"daily":{"2026-10-16":3}},"list":[{"a":{"b":0.5}}],"s":"str"}',
+                             r.execute_command('JSON.GET', 'test', '.'))
+
+            # nothing is created when the path can't be
+            data = r.execute_command('JSON.GET', 'test', '.')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.s.x', 1, 'CREATE')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.list[3].x', 1, 'CREATE')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.x.y', '"a"', 'CREATE')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '$..x', 1, 'CREATE')
+            self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+
+            # CREATE is only supported by JSON.NUMINCRBY
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMMULTBY', 'test', '.x', 2, 'CREATE')
+
+    def testBigNumbers(self):
+        """Test that numbers are stored with their exact text and incremented exactly"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            data = '{"id":12345678901234567890123,"d":0.1000000000000000055,"e":1.5e+300}'
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', data))
+            self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+            self.assertEqual('integer', r.execute_command('JSON.TYPE', 'test', '.id'))
+            for _ in r.retry_with_rdb_reload():
+                self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.id', 1)
+            self.assertEqual('1.1000000000000000055', r.execute_command('JSON.NUMINCRBY', 'test', '.d', 1))
+            self.assertEqual('2.2000000000000000110', r.execute_command('JSON.NUMMULTBY', 'test', '.d', 2))
+            self.assertEqual('12345678901234567890123', r.execute_command('JSON.GET', 'test', '.id'))
+            self.assertEqual('123', r.execute_command('JSON.NUMINCRBY', 'test', '.id', '-12345678901234567890000'))
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.d', '0.1'))
+            self.assertEqual('0.3', r.execute_command('JSON.NUMINCRBY', 'test', '.d', '0.2'))
+            self.assertEqual('1.0', r.execute_command('JSON.NUMINCRBY', 'test', '.d', '0.7'))
+            self.assertEqual('number', r.execute_command('JSON.TYPE', 'test', '.d'))
+
+            # numbers out of the double range are rejected
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.SET', 'test', '.x', '1e400')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.ARRAPPEND', 'test', '.', '[1e400]')
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.d', '1e400')
+            self.assertEqual('number', r.execute_command('JSON.TYPE', 'test', '.e'))
+            self.assertEqual(1.5e+300, float(r.execute_command('JSON.RESP', 'test', '.e')))
+
+            # a large exponent falls back to doubles instead of aligning every digit
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.d', '1e-1000000'))
+            self.assertEqual('1.0', r.execute_command('JSON.NUMINCRBY', 'test', '.d', 1))
+
+            # equal numbers with a different text match
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '[1.5,2]'))
+            self.assertEqual(2, r.execute_command('JSON.ARRADDUNIQUE', 'test', '.', '1.50', '2.0'))
+            self.assertEqual(0, r.execute_command('JSON.ARRINDEX', 'test', '.', '1.50'))
+            self.assertEqual(1, r.execute_command('JSON.ARRREM', 'test', '.', '1.50'))
+            self.assertEqual('[2]', r.execute_command('JSON.GET', 'test', '.'))
+    
+    def testNumOverflow(self):
+        """Test 64-bit integer overflow in the JSON.NUM* commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"i":9223372036854775807,"u":18446744073709551614,"n":-9223372036854775807}'))
+            self.assertEqual('9223372036854775808', r.execute_command('JSON.NUMINCRBY', 'test', '.i', 1))
+            self.assertEqual('integer', r.execute_command('JSON.TYPE', 'test', '.i'))
+            self.assertEqual('9223372036854775807', r.execute_command('JSON.NUMINCRBY', 'test', '.i', -1))
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMINCRBY', 'test', '.u', 1))
+            self.assertEqual('-1', r.execute_command('JSON.NUMINCRBY', 'test', '.u', '-18446744073709551616'))
+
+            # overflow is an error and leaves the document untouched
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.u', '18446744073709551615'))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.NUMINCRBY', 'test', '.u', 1)
+            self.assertIn('overflow', str(cm.exception))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMMULTBY', 'test', '.n', 2)
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMPOWBY', 'test', '.n', 3)
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.GET', 'test', '.u'))
+            self.assertEqual('-9223372036854775807', r.execute_command('JSON.GET', 'test', '.n'))
+
+            # SATURATE clamps the result to the 64-bit integer range
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMINCRBY', 'test', '.u', 1, 'SATURATE'))
+            self.assertEqual('-9223372036854775808', r.execute_command('JSON.NUMMULTBY', 'test', '.n', 2, 'SATURATE'))
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMPOWBY', 'test', '.i', 3, 'saturate'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.u', 1, 'FOO')
+
+            # operands bigger than 64 bits are computed exactly, with the same bounds
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.u', '18446744073709551616')
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMINCRBY', 'test', '.u', '18446744073709551616', 'SATURATE'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.b', '18446744073709551616'))
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMINCRBY', 'test', '.b', -1))
+
+            # and so are operands at the 128-bit boundary
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.b', '170141183460469231731687303715884105727'))
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '.b', 1)
+            self.assertEqual('170141183460469231731687303715884105727', r.execute_command('JSON.GET', 'test', '.b'))
+            self.assertEqual('18446744073709551615', r.execute_command('JSON.NUMINCRBY', 'test', '.b', 1, 'SATURATE'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.b', '-170141183460469231731687303715884105728'))
+            self.assertEqual('-9223372036854775808', r.execute_command('JSON.NUMMULTBY', 'test', '.b', 2, 'SATURATE'))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.b', '-170141183460469231731687303715884105729'))
+            self.assertEqual('1', r.execute_command('JSON.NUMPOWBY', 'test', '.b', 0))
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.b', '-1'))
+            self.assertEqual('1', r.execute_command('JSON.NUMPOWBY', 'test', '.b', '340282366920938463463374607431768211456'))
+
+            # a negative exponent has no integer result
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.i', '2'))
+            self.assertEqual('0.5', r.execute_command('JSON.NUMPOWBY', 'test', '.i', -1))
+
+    def testNumNonFinite(self):
+        """Test that the JSON.NUM* commands reject NaN and infinite results"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            data = '{"f":1.5,"n":-8,"a":[2.5,"x"]}'
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', data))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.NUMPOWBY', 'test', '.f', 10000)
+            self.assertIn('not a finite number', str(cm.exception))
+            self.assertIn("$['f']", str(cm.exception))
+            self.assertIn('1.5', str(cm.exception))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.NUMPOWBY', 'test', '.n', 0.5)
+            self.assertIn('not a finite number', str(cm.exception))
+
+            # the error names the path of the matched value, not the pattern
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.NUMMULTBY', 'test', '$..[0]', '1e308')
+            self.assertIn("$['a'][0]", str(cm.exception))
+
+            # a failure on any of the matches leaves every match untouched
+            self.assertRaises(redis.exceptions.ResponseError, r.execute_command, 'JSON.NUMINCRBY', 'test', '$.a[*]', 1)
+            self.assertEqual(data, r.execute_command('JSON.GET', 'test', '.'))
+
     
     def testStrCommands(self):
         """Test JSON.STRAPPEND and JSON.STRLEN commands"""
@@ -644,6 +1369,59 @@ class ReJSONTestCase(BaseReJSONTest):
             self.assertEqual(3, r.execute_command('JSON.STRLEN', 'test', '.'))
             self.assertEqual(6, r.execute_command('JSON.STRAPPEND', 'test', '.', '"bar"'))
             self.assertEqual('"foobar"', r.execute_command('JSON.GET', 'test', '.'))
+
+            # test lengths and ranges of non-ASCII strings
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', json.dumps(u'héllo\U0001F600')))
+            self.assertEqual(10, r.execute_command('JSON.STRLEN', 'test', '.'))
+            self.assertEqual(10, r.execute_command('JSON.STRLEN', 'test', '.', 'UNIT', 'BYTES'))
+            self.assertEqual(6, r.execute_command('JSON.STRLEN', 'test', '.', 'UNIT', 'CHARS'))
+            self.assertEqual(7, r.execute_command('JSON.STRLEN', 'test', '.', 'UNIT', 'utf16'))
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.STRLEN', 'test', '.', 'UNIT', 'WORDS')
+
+            self.assertEqual(u'éll', json.loads(r.execute_command('JSON.STRRANGE', 'test', '.', 1, 3)))
+            self.assertEqual(u'o\U0001F600', json.loads(r.execute_command('JSON.STRRANGE', 'test', '.', -2, -1)))
+            self.assertEqual(u'héllo\U0001F600', json.loads(r.execute_command('JSON.STRRANGE', 'test', '.', 0, 99)))
+            self.assertEqual(u'', json.loads(r.execute_command('JSON.STRRANGE', 'test', '.', 3, 1)))
+            self.assertEqual(u'', json.loads(r.execute_command('JSON.STRRANGE', 'test', '.', 6, 9)))
+
+    def testStrSetRangeAndPrependCommands(self):
+        """Test JSON.STRSETRANGE and JSON.STRPREPEND commands"""
+
+        with self.redis() as r:
+            r.client_setname(self._testMethodName)
+            r.flushdb()
+
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.', '{"a": "world", "b": {"a": "wide"}, "n": 1}'))
+            self.assertEqual(6, r.execute_command('JSON.STRPREPEND', 'test', '.a', '"-"'))
+            self.assertEqual(11, r.execute_command('JSON.STRPREPEND', 'test', '.a', '"hello"'))
+            self.assertEqual('"hello-world"', r.execute_command('JSON.GET', 'test', '.a'))
+
+            self.assertEqual(11, r.execute_command('JSON.STRSETRANGE', 'test', '.a', 5, '" "'))
+            self.assertEqual(12, r.execute_command('JSON.STRSETRANGE', 'test', '.a', -1, '"D!"'))
+            self.assertEqual(13, r.execute_command('JSON.STRSETRANGE', 'test', '.a', 12, '"?"'))
+            self.assertEqual('"hello worlD!?"', r.execute_command('JSON.GET', 'test', '.a'))
+
+            # offsets are in characters, the reply is in bytes
+            self.assertOk(r.execute_command('JSON.SET', 'test', '.a', json.dumps(u'héllo')))
+            self.assertEqual(6, r.execute_command('JSON.STRSETRANGE', 'test', '.a', 2, '"L"'))
+            self.assertEqual(u'héLlo', json.loads(r.execute_command('JSON.GET', 'test', '.a')))
+            self.assertEqual(5, r.execute_command('JSON.STRLEN', 'test', '.a', 'UNIT', 'CHARS'))
+            self.assertEqual(6, r.execute_command('JSON.STRSETRANGE', 'test', '.a', 5, '""'))
+
+            # every matched path is updated
+            r.execute_command('JSON.STRPREPEND', 'test', '$..a', '"x"')
+            self.assertEqual(u'xhéLlo', json.loads(r.execute_command('JSON.GET', 'test', '.a')))
+            self.assertEqual(9, r.execute_command('JSON.STRPREPEND', 'test', '.a', json.dumps(u'é')))
+            self.assertEqual('"xwide"', r.execute_command('JSON.GET', 'test', '.b.a'))
+
+            # test wrong types and an offset out of range
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.STRPREPEND', 'test', '.n', '"x"')
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.STRSETRANGE', 'test', '.a', 0, '1')
+            with self.assertRaises(redis.exceptions.ResponseError) as cm:
+                r.execute_command('JSON.STRSETRANGE', 'test', '.a', 99, '"x"')
     
     def testRespCommand(self):
         """Test JSON.RESP command"""